@@ -0,0 +1,39 @@
+use std::fmt;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Displays a path without losing information, unlike `Path::display` which
+/// replaces bytes that are not valid UTF-8 with U+FFFD.
+///
+/// Invalid bytes and ASCII control characters are written as `\xNN` escapes,
+/// other control characters as `\u{NNNN}` and backslashes are doubled, so two
+/// different names never print the same.
+pub struct Escaped<'a>(&'a Path);
+
+/// Returns a value that displays the path with escapes for non-UTF-8 bytes.
+///
+/// # Arguments
+///
+/// * `path` - The path to display.
+pub fn escaped(path: &Path) -> Escaped<'_> {
+    Escaped(path)
+}
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for chunk in self.0.as_os_str().as_bytes().utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '\\' => f.write_str("\\\\")?,
+                    c if c.is_ascii_control() => write!(f, "\\x{:02x}", c as u32)?,
+                    c if c.is_control() => write!(f, "\\u{{{:04x}}}", c as u32)?,
+                    c => write!(f, "{c}")?,
+                }
+            }
+            for b in chunk.invalid() {
+                write!(f, "\\x{b:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use crate::escape::escaped;
+use crate::interrupt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// How the findings of a hygiene scan should be cleaned up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Cleanup {
+    /// Print what would be removed without touching the file system.
+    DryRun,
+    /// Ask for confirmation before removing each entry.
+    Interactive,
+}
+
+/// Entries found by a hygiene scan.
+///
+/// Empty directories are stored children first, so removing them in order
+/// never trips over a directory that still holds an empty subdirectory.
+#[derive(Debug, Default)]
+pub struct HygieneReport {
+    pub empty_files: Vec<PathBuf>,
+    pub empty_dirs: Vec<PathBuf>,
+    pub broken_symlinks: Vec<PathBuf>,
+    /// The scanned directory, when it is empty. It is reported but
+    /// never removed, so it is not one of `empty_dirs`.
+    pub empty_root: Option<PathBuf>,
+}
+
+impl HygieneReport {
+    /// Prints every finding grouped by kind, followed by a one line summary.
+    pub fn print(&self) {
+        for path in &self.empty_files {
+            println!("Empty file: {}", escaped(path));
+        }
+        for path in &self.empty_dirs {
+            println!("Empty directory: {}", escaped(path));
+        }
+        for path in &self.broken_symlinks {
+            println!("Broken symlink: {}", escaped(path));
+        }
+        if let Some(path) = &self.empty_root {
+            println!("Scanned directory is empty: {}", escaped(path));
+        }
+        println!(
+            "Found {} empty files, {} empty directories and {} broken symlinks",
+            self.empty_files.len(),
+            self.empty_dirs.len(),
+            self.broken_symlinks.len()
+        );
+    }
+}
+
+/// Recursively scans the directory at the given path for zero-byte files,
+/// empty directories and dangling symlinks.
+///
+/// Symlinks are never followed, so a link to a directory is checked for its
+/// target but not descended into. A directory counts as empty when it only
+/// contains directories that are themselves empty, the scanned directory
+/// itself is only reported as such. Directories and entries
+/// that cannot be read are reported and skipped instead of aborting the scan,
+/// and a directory holding them is never counted as empty.
+///
+/// # Arguments
+///
+/// * `path` - A reference to a `Path` that represents the directory to scan.
+pub fn scan(path: &Path) -> HygieneReport {
+    let mut report = HygieneReport::default();
+    if scan_dir(path, &mut report) {
+        report.empty_root = Some(path.to_path_buf());
+    }
+    report
+}
+
+// Scans a single directory and returns whether it is recursively empty.
+fn scan_dir(path: &Path, report: &mut HygieneReport) -> bool {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", escaped(path), e);
+            return false;
+        }
+    };
+    let mut is_empty = true;
+    for entry in entries {
+        // A directory that was not fully scanned must not be reported as empty
+        if interrupt::interrupted() {
+            return false;
+        }
+        // Use the metadata of the entry itself rather than of what it points to
+        let (path, metadata) = match entry.and_then(|entry| {
+            let path = entry.path();
+            fs::symlink_metadata(&path).map(|metadata| (path, metadata))
+        }) {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Error reading an entry of {}: {}", escaped(path), e);
+                is_empty = false;
+                continue;
+            }
+        };
+        let file_type = metadata.file_type();
+
+        if file_type.is_symlink() {
+            // A link whose target cannot be resolved (missing or looping) is dangling
+            if fs::metadata(&path).is_err() {
+                report.broken_symlinks.push(path);
+            }
+            is_empty = false;
+        } else if file_type.is_dir() {
+            if scan_dir(&path, report) {
+                report.empty_dirs.push(path);
+            } else {
+                is_empty = false;
+            }
+        } else {
+            if file_type.is_file() && metadata.len() == 0 {
+                report.empty_files.push(path);
+            }
+            is_empty = false;
+        }
+    }
+    is_empty
+}
+
+/// Removes the entries found by a hygiene scan according to the cleanup mode.
+///
+/// Files and symlinks are removed before directories so that a directory
+/// declined by the user does not stop the rest of the cleanup. Files and
+/// symlinks are checked again right before removal, and the ones that gained
+/// data or were repaired since the scan are kept. Failures are reported and
+/// skipped.
+///
+/// # Arguments
+///
+/// * `report` - The findings of a previous call to `scan`.
+/// * `mode` - Whether to only print the removals or to confirm each one.
+pub fn cleanup(report: &HygieneReport, mode: Cleanup) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    let files = report.empty_files.iter().chain(&report.broken_symlinks);
+    for path in files {
+        if interrupt::interrupted() {
+            return Ok(());
+        }
+        if confirm(path, mode, &mut input)? {
+            // The entry may have changed while waiting for the answer
+            if !still_removable(path) {
+                eprintln!("Skipped {}: changed since the scan", escaped(path));
+                continue;
+            }
+            if let Err(e) = fs::remove_file(path) {
+                eprintln!("Failed to remove {}: {}", escaped(path), e);
+            }
+        }
+    }
+    for path in &report.empty_dirs {
+        if interrupt::interrupted() {
+            return Ok(());
+        }
+        if confirm(path, mode, &mut input)? {
+            if let Err(e) = fs::remove_dir(path) {
+                eprintln!("Failed to remove {}: {}", escaped(path), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Returns whether a file is still empty, or a symlink still dangling, as
+// found by the scan. Empty directories need no check since removing a
+// directory that is no longer empty fails.
+fn still_removable(path: &Path) -> bool {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::metadata(path).is_err(),
+        Ok(metadata) => metadata.is_file() && metadata.len() == 0,
+        Err(_) => false,
+    }
+}
+
+// Returns whether the entry should be removed, prompting in interactive mode.
+fn confirm(path: &Path, mode: Cleanup, input: &mut impl BufRead) -> io::Result<bool> {
+    match mode {
+        Cleanup::DryRun => {
+            println!("Would remove: {}", escaped(path));
+            Ok(false)
+        }
+        Cleanup::Interactive => {
+            print!("Remove {}? [y/N] ", escaped(path));
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            input.read_line(&mut answer)?;
+            Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+        }
+    }
+}
@@ -1,53 +1,120 @@
-use std::env;
+mod escape;
+mod hygiene;
+mod interrupt;
+mod ncdu;
+
+use clap::Parser;
+use escape::escaped;
+use hygiene::Cleanup;
 use std::fs;
-use std::io;
-use std::path::Path;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+// Command-line options for the program.
+#[derive(Parser)]
+#[command(name = "crawl", about = "Crawl a directory and report its entries")]
+struct Opts {
+    // Directory to crawl
+    #[clap(help = "Path to crawl")]
+    path: PathBuf,
+
+    // Report empty files, empty directories and broken symlinks
+    #[clap(
+        long,
+        help = "Report empty files, empty directories and broken symlinks"
+    )]
+    hygiene: bool,
+
+    // Clean up the entries found by the hygiene scan
+    #[clap(
+        long,
+        value_enum,
+        requires = "hygiene",
+        help = "Clean up the hygiene findings"
+    )]
+    cleanup: Option<Cleanup>,
+
+    // Export the crawl in the ncdu JSON format, "-" writes to stdout
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with = "hygiene",
+        help = "Export the crawl in ncdu JSON format to FILE (- for stdout)"
+    )]
+    ncdu: Option<PathBuf>,
+}
 
 /// Recursively crawls the directory at the given path and prints the paths of entries.
 ///
 /// # Arguments
 ///
 /// * `path` - A reference to a `Path` that represents the directory to crawl.
+///
 /// e.g. cargo run /path/to/directory
-
 fn crawl(path: &Path) -> io::Result<()> {
     // Read the directory entries
     for entry in fs::read_dir(path)? {
+        if interrupt::interrupted() {
+            break;
+        }
         let entry = entry?;
-        println!("Entry path: {}", entry.path().display());
+        println!("Entry path: {}", escaped(&entry.path()));
         let path = entry.path();
         if path.is_dir() {
             // Recursively crawl subdirectories
             //crawl(&path)?;
         } else if path.is_file() {
-            println!("File path: {}", path.display());
+            println!("File path: {}", escaped(&path));
         } else {
-            println!("Unknown type: {}", path.display());
+            println!("Unknown type: {}", escaped(&path));
         }
     }
     Ok(())
 }
 
-fn main() {
-    // Collect command-line arguments
-    let args: Vec<String> = env::args().collect();
+// Runs the hygiene scan, prints its findings and cleans up if requested.
+// e.g. cargo run -- --hygiene --cleanup dry-run /path/to/directory
+fn hygiene(path: &Path, mode: Option<Cleanup>) -> io::Result<()> {
+    let report = hygiene::scan(path);
+    report.print();
+    // Never clean up after an interrupted scan
+    if let Some(mode) = mode.filter(|_| !interrupt::interrupted()) {
+        hygiene::cleanup(&report, mode)?;
+    }
+    Ok(())
+}
 
-    // Check if the user provided a path argument
-    if args.len() < 2 {
-        eprintln!("Usage: {} <path>", args[0]);
-        return;
+// Exports the crawl for browsing with `ncdu -f FILE`.
+// e.g. cargo run -- --ncdu scan.json /path/to/directory
+fn export_ncdu(path: &Path, output: &Path) -> io::Result<()> {
+    if output == Path::new("-") {
+        return ncdu::export(path, BufWriter::new(io::stdout().lock()));
     }
+    ncdu::export(path, BufWriter::new(fs::File::create(output)?))
+}
 
-    let path = Path::new(&args[1]);
+fn main() {
+    // Parse command-line arguments
+    let opts = Opts::parse();
+    if let Err(e) = interrupt::install() {
+        eprintln!("Failed to install signal handlers: {}", e);
+    }
+    let path = opts.path.as_path();
 
     // Check if the provided path exists
     if !path.exists() {
-        eprintln!("{} does not exist", path.display());
+        eprintln!("{} does not exist", escaped(path));
         return;
     }
 
     // Attempt to crawl the directory and handle any errors
-    if let Err(e) = crawl(&path) {
-        eprintln!("Error while crawling {}: {}", path.display(), e);
+    let result = match (&opts.ncdu, opts.hygiene) {
+        (Some(output), _) => export_ncdu(path, output),
+        (None, true) => hygiene(path, opts.cleanup),
+        (None, false) => crawl(path),
+    };
+    if let Err(e) = result {
+        eprintln!("Error while crawling {}: {}", escaped(path), e);
     }
+    interrupt::exit_if_interrupted();
 }
@@ -0,0 +1,144 @@
+use crate::interrupt;
+use std::fs::{self, Metadata};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Version of the ncdu export format written by this module
+const MAJOR_VERSION: u32 = 1;
+const MINOR_VERSION: u32 = 2;
+
+/// Recursively crawls the directory at the given path and writes it in the
+/// ncdu JSON export format, which `ncdu -f <file>` can browse interactively.
+///
+/// Directories are arrays whose first element describes the directory itself,
+/// followed by one element per entry. Symlinks are not followed and are
+/// exported as non-regular files. Directories that cannot be read are
+/// exported with `read_error` set instead of aborting the crawl. When the
+/// crawl is interrupted the open arrays are closed, so the export stays valid.
+///
+/// # Arguments
+///
+/// * `path` - A reference to a `Path` that represents the directory to crawl.
+/// * `out` - Where to write the JSON export.
+pub fn export(path: &Path, mut out: impl Write) -> io::Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    writeln!(
+        out,
+        "[{MAJOR_VERSION},{MINOR_VERSION},{{\"progname\":\"{}\",\"progver\":\"{}\",\"timestamp\":{timestamp}}},",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )?;
+
+    // ncdu names the root after its absolute path
+    let root = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let metadata = fs::symlink_metadata(&root)?;
+    write_dir(
+        &mut out,
+        &root,
+        root.as_os_str().as_bytes(),
+        &metadata,
+        None,
+    )?;
+    writeln!(out, "]")?;
+    out.flush()
+}
+
+// Writes a directory array: its own info object followed by every entry.
+fn write_dir(
+    out: &mut impl Write,
+    path: &Path,
+    name: &[u8],
+    metadata: &Metadata,
+    parent_dev: Option<u64>,
+) -> io::Result<()> {
+    write!(out, "[")?;
+    let entries = fs::read_dir(path).and_then(|entries| entries.collect::<io::Result<Vec<_>>>());
+    write_info(out, name, metadata, parent_dev, entries.is_err())?;
+
+    for entry in entries.unwrap_or_default() {
+        if interrupt::interrupted() {
+            break;
+        }
+        let path = entry.path();
+        let name = entry.file_name();
+        writeln!(out, ",")?;
+        match fs::symlink_metadata(&path) {
+            Ok(child) if child.is_dir() => {
+                write_dir(out, &path, name.as_bytes(), &child, Some(metadata.dev()))?
+            }
+            Ok(child) => write_info(out, name.as_bytes(), &child, Some(metadata.dev()), false)?,
+            // The entry vanished or cannot be inspected, keep its name
+            Err(_) => {
+                write!(out, "{{\"name\":")?;
+                write_string(out, name.as_bytes())?;
+                write!(out, ",\"read_error\":true}}")?;
+            }
+        }
+    }
+    write!(out, "]")
+}
+
+// Writes the info object describing a single entry.
+fn write_info(
+    out: &mut impl Write,
+    name: &[u8],
+    metadata: &Metadata,
+    parent_dev: Option<u64>,
+    read_error: bool,
+) -> io::Result<()> {
+    write!(out, "{{\"name\":")?;
+    write_string(out, name)?;
+    // Apparent size and disk usage, st_blocks is always in 512 byte units
+    write!(
+        out,
+        ",\"asize\":{},\"dsize\":{}",
+        metadata.size(),
+        metadata.blocks() * 512
+    )?;
+    // The device is only exported where it changes, i.e. at mount points
+    if parent_dev != Some(metadata.dev()) {
+        write!(out, ",\"dev\":{}", metadata.dev())?;
+    }
+    write!(out, ",\"ino\":{}", metadata.ino())?;
+    if !metadata.is_dir() && metadata.nlink() > 1 {
+        write!(out, ",\"hlnkc\":true,\"nlink\":{}", metadata.nlink())?;
+    }
+    if !metadata.is_dir() && !metadata.is_file() {
+        write!(out, ",\"notreg\":true")?;
+    }
+    if read_error {
+        write!(out, ",\"read_error\":true")?;
+    }
+    // Extended information, shown by ncdu when browsing with -e
+    write!(
+        out,
+        ",\"uid\":{},\"gid\":{},\"mode\":{},\"mtime\":{}}}",
+        metadata.uid(),
+        metadata.gid(),
+        metadata.mode(),
+        metadata.mtime()
+    )
+}
+
+// Writes a JSON string. Quotes, backslashes and control characters are
+// escaped, every other byte is written as is. Like ncdu itself this keeps
+// file names that are not valid UTF-8 byte for byte.
+fn write_string(out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for &b in bytes {
+        match b {
+            b'"' => out.write_all(b"\\\"")?,
+            b'\\' => out.write_all(b"\\\\")?,
+            b'\n' => out.write_all(b"\\n")?,
+            b'\t' => out.write_all(b"\\t")?,
+            0x00..=0x1f | 0x7f => write!(out, "\\u{:04x}", b)?,
+            _ => out.write_all(&[b])?,
+        }
+    }
+    out.write_all(b"\"")
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::fs::FileExt;
+
+/// The four magic bytes every ELF file starts with.
+pub const MAGIC: &[u8] = b"\x7fELF";
+
+// Program header types
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+
+// Section header type of the full symbol table, removed by `strip`
+const SHT_SYMTAB: u32 = 2;
+
+// Dynamic section tags
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_STRSZ: u64 = 10;
+
+/// Summary of an ELF binary as reported by the classifier.
+#[derive(Debug)]
+pub struct ElfInfo {
+    pub bits: u8,
+    pub big_endian: bool,
+    pub machine: u16,
+    pub kind: u16,
+    pub interpreter: Option<String>,
+    pub needed: Vec<String>,
+    pub stripped: bool,
+}
+
+impl ElfInfo {
+    /// Returns the name of the target architecture from the `e_machine` field.
+    pub fn architecture(&self) -> String {
+        let name = match self.machine {
+            2 => "SPARC",
+            3 => "x86",
+            8 => "MIPS",
+            20 => "PowerPC",
+            21 => "PowerPC64",
+            22 => "S390",
+            40 => "ARM",
+            43 => "SPARC V9",
+            62 => "x86-64",
+            183 => "AArch64",
+            243 => "RISC-V",
+            258 => "LoongArch",
+            other => return format!("unknown ({other})"),
+        };
+        name.to_string()
+    }
+
+    /// Returns the object file type from the `e_type` field.
+    pub fn file_type(&self) -> &'static str {
+        match self.kind {
+            1 => "relocatable",
+            2 => "executable",
+            3 => "shared object",
+            4 => "core dump",
+            _ => "unknown",
+        }
+    }
+
+    /// A binary is dynamically linked when it asks for a program interpreter
+    /// or depends on shared libraries. Static PIE binaries have neither.
+    pub fn is_dynamic(&self) -> bool {
+        self.interpreter.is_some() || !self.needed.is_empty()
+    }
+}
+
+impl fmt::Display for ElfInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let endian = if self.big_endian { "big" } else { "little" };
+        writeln!(
+            f,
+            "    Architecture: {}, {}-bit, {}-endian",
+            self.architecture(),
+            self.bits,
+            endian
+        )?;
+        writeln!(f, "    Type: {}", self.file_type())?;
+        let linkage = if self.is_dynamic() {
+            "dynamic"
+        } else {
+            "static"
+        };
+        writeln!(f, "    Linkage: {linkage}")?;
+        if let Some(interpreter) = &self.interpreter {
+            writeln!(f, "    Interpreter: {interpreter}")?;
+        }
+        if !self.needed.is_empty() {
+            writeln!(f, "    Needed: {}", self.needed.join(", "))?;
+        }
+        write!(
+            f,
+            "    Stripped: {}",
+            if self.stripped { "yes" } else { "no" }
+        )
+    }
+}
+
+// Reads fixed width integers out of a byte slice in the file's byte order.
+struct Fields<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Fields<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+        self.data
+            .get(offset..offset + N)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or_else(|| invalid("ELF structure is truncated"))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16> {
+        let bytes = self.bytes(offset)?;
+        Ok(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32> {
+        let bytes = self.bytes(offset)?;
+        Ok(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64> {
+        let bytes = self.bytes(offset)?;
+        Ok(match self.big_endian {
+            true => u64::from_be_bytes(bytes),
+            false => u64::from_le_bytes(bytes),
+        })
+    }
+
+    // Reads an address or offset sized field, which is 4 bytes in 32-bit files
+    fn word(&self, offset: usize, bits: u8) -> Result<u64> {
+        match bits {
+            32 => self.u32(offset).map(u64::from),
+            _ => self.u64(offset),
+        }
+    }
+}
+
+// A loadable or dynamic segment from the program header table.
+struct Segment {
+    kind: u32,
+    offset: u64,
+    vaddr: u64,
+    filesz: u64,
+}
+
+/// Parses the ELF header of the given file and reports its architecture,
+/// linkage, interpreter, needed libraries and whether it is stripped.
+///
+/// Only the headers, the interpreter string and the dynamic section are read,
+/// so large binaries are inspected without loading them into memory.
+///
+/// # Arguments
+///
+/// * `file` - An open file whose first bytes match `MAGIC`.
+pub fn inspect(file: &File) -> Result<ElfInfo> {
+    let len = file.metadata()?.len();
+    let ident = read_at(file, 0, 64.min(len), len)?;
+    if !ident.starts_with(MAGIC) || ident.len() < 52 {
+        return Err(invalid("not an ELF file"));
+    }
+    let bits = match ident[4] {
+        1 => 32,
+        2 => 64,
+        _ => return Err(invalid("unknown ELF class")),
+    };
+    let header = Fields {
+        data: &ident,
+        big_endian: ident[5] == 2,
+    };
+
+    // The header layout only differs in the width of the address fields
+    let kind = header.u16(16)?;
+    let machine = header.u16(18)?;
+    let (phoff, shoff, rest) = match bits {
+        32 => (header.word(28, bits)?, header.word(32, bits)?, 42),
+        _ => (header.word(32, bits)?, header.word(40, bits)?, 54),
+    };
+    let phentsize = header.u16(rest)? as u64;
+    let phnum = header.u16(rest + 2)? as u64;
+    let shentsize = header.u16(rest + 4)? as u64;
+    let shnum = header.u16(rest + 6)? as u64;
+
+    // Collect the program headers we care about
+    let table = read_at(file, phoff, phentsize * phnum, len)?;
+    let table = Fields {
+        data: &table,
+        big_endian: header.big_endian,
+    };
+    let mut segments = Vec::new();
+    for i in 0..phnum as usize {
+        let base = i * phentsize as usize;
+        let segment = match bits {
+            32 => Segment {
+                kind: table.u32(base)?,
+                offset: table.word(base + 4, bits)?,
+                vaddr: table.word(base + 8, bits)?,
+                filesz: table.word(base + 16, bits)?,
+            },
+            _ => Segment {
+                kind: table.u32(base)?,
+                offset: table.word(base + 8, bits)?,
+                vaddr: table.word(base + 16, bits)?,
+                filesz: table.word(base + 32, bits)?,
+            },
+        };
+        segments.push(segment);
+    }
+
+    let interpreter = match segments.iter().find(|s| s.kind == PT_INTERP) {
+        Some(segment) => {
+            let bytes = read_at(file, segment.offset, segment.filesz, len)?;
+            Some(c_string(&bytes))
+        }
+        None => None,
+    };
+
+    let needed = match segments.iter().find(|s| s.kind == PT_DYNAMIC) {
+        Some(dynamic) => needed_libraries(file, dynamic, &segments, bits, header.big_endian, len)?,
+        None => Vec::new(),
+    };
+
+    // A binary without a symbol table section has been stripped
+    let mut stripped = true;
+    if shoff != 0 && shentsize > 0 {
+        let table = read_at(file, shoff, shentsize * shnum, len)?;
+        let table = Fields {
+            data: &table,
+            big_endian: header.big_endian,
+        };
+        for i in 0..shnum as usize {
+            if table.u32(i * shentsize as usize + 4)? == SHT_SYMTAB {
+                stripped = false;
+                break;
+            }
+        }
+    }
+
+    Ok(ElfInfo {
+        bits,
+        big_endian: header.big_endian,
+        machine,
+        kind,
+        interpreter,
+        needed,
+        stripped,
+    })
+}
+
+// Walks the dynamic section and resolves every DT_NEEDED entry through the
+// dynamic string table.
+fn needed_libraries(
+    file: &File,
+    dynamic: &Segment,
+    segments: &[Segment],
+    bits: u8,
+    big_endian: bool,
+    len: u64,
+) -> Result<Vec<String>> {
+    let data = read_at(file, dynamic.offset, dynamic.filesz, len)?;
+    let entries = Fields {
+        data: &data,
+        big_endian,
+    };
+    let entry_size = bits as usize / 4;
+
+    let mut names = Vec::new();
+    let mut strtab = None;
+    let mut strsz = None;
+    for base in (0..data.len() / entry_size).map(|i| i * entry_size) {
+        let tag = entries.word(base, bits)?;
+        let value = entries.word(base + entry_size / 2, bits)?;
+        match tag {
+            DT_NULL => break,
+            DT_NEEDED => names.push(value),
+            DT_STRTAB => strtab = Some(value),
+            DT_STRSZ => strsz = Some(value),
+            _ => {}
+        }
+    }
+
+    let (Some(strtab), Some(strsz)) = (strtab, strsz) else {
+        return Ok(Vec::new());
+    };
+    // DT_STRTAB holds a virtual address, map it back to a file offset
+    let (segment, delta) = segments
+        .iter()
+        .filter(|s| s.kind == PT_LOAD)
+        .find_map(|s| {
+            let delta = strtab.checked_sub(s.vaddr)?;
+            (delta < s.filesz).then_some((s, delta))
+        })
+        .ok_or_else(|| invalid("dynamic string table is not mapped"))?;
+    let offset = segment
+        .offset
+        .checked_add(delta)
+        .ok_or_else(|| invalid("dynamic string table offset overflows"))?;
+    let strings = read_at(file, offset, strsz, len)?;
+
+    Ok(names
+        .into_iter()
+        .filter_map(|name| strings.get(name as usize..))
+        .map(c_string)
+        .collect())
+}
+
+// Reads `size` bytes at `offset`, refusing ranges that run past the end of the file.
+fn read_at(file: &File, offset: u64, size: u64, len: u64) -> Result<Vec<u8>> {
+    if offset.checked_add(size).is_none_or(|end| end > len) {
+        return Err(invalid("ELF structure points past the end of the file"));
+    }
+    let mut buffer = vec![0; size as usize];
+    file.read_exact_at(&mut buffer, offset)?;
+    Ok(buffer)
+}
+
+// Decodes a NUL terminated string, replacing invalid UTF-8.
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
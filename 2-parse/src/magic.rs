@@ -0,0 +1,45 @@
+// Known magic numbers as (format name, offset, bytes), mostly compressed,
+// archived or encrypted formats whose contents are expected to look random.
+const SIGNATURES: &[(&str, usize, &[u8])] = &[
+    ("ELF", 0, b"\x7fELF"),
+    ("gzip", 0, b"\x1f\x8b"),
+    ("bzip2", 0, b"BZh"),
+    ("xz", 0, b"\xfd7zXZ\x00"),
+    ("zstd", 0, b"\x28\xb5\x2f\xfd"),
+    ("lz4", 0, b"\x04\x22\x4d\x18"),
+    ("zip", 0, b"PK\x03\x04"),
+    ("7z", 0, b"7z\xbc\xaf\x27\x1c"),
+    ("rar", 0, b"Rar!\x1a\x07"),
+    ("tar", 257, b"ustar"),
+    ("png", 0, b"\x89PNG\r\n\x1a\n"),
+    ("jpeg", 0, b"\xff\xd8\xff"),
+    ("gif", 0, b"GIF8"),
+    ("webp", 8, b"WEBP"),
+    ("pdf", 0, b"%PDF-"),
+    ("mp4", 4, b"ftyp"),
+    ("ogg", 0, b"OggS"),
+    ("flac", 0, b"fLaC"),
+    ("mp3", 0, b"ID3"),
+    ("sqlite", 0, b"SQLite format 3\x00"),
+    ("luks", 0, b"LUKS\xba\xbe"),
+    ("age", 0, b"age-encryption.org/"),
+    ("gpg", 0, b"\x85\x01"),
+    ("gpg", 0, b"\x85\x02"),
+];
+
+/// Returns the name of the format whose magic number the header starts with,
+/// if it is one of the known signatures.
+///
+/// # Arguments
+///
+/// * `header` - The first bytes of the file, at least 262 to detect tar archives.
+pub fn identify(header: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(_, offset, bytes)| {
+            header
+                .get(*offset..)
+                .is_some_and(|rest| rest.starts_with(bytes))
+        })
+        .map(|(name, _, _)| *name)
+}
@@ -1,7 +1,97 @@
-use std::env;
+mod elf;
+mod entropy;
+mod escape;
+mod interrupt;
+mod magic;
+mod summary;
+mod text;
+mod throttle;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use escape::escaped;
 use std::fs;
-use std::io::{BufReader, Read, Result};
-use std::path::Path;
+use std::io::{BufReader, Read, Result, Seek};
+use std::path::{Path, PathBuf};
+use summary::Summary;
+use throttle::{IoPriority, Throttle};
+
+// Command-line options for the program.
+#[derive(Parser)]
+#[command(name = "parse", about = "Crawl a directory and classify its files")]
+struct Opts {
+    // Directory to crawl
+    #[clap(help = "Path to crawl")]
+    path: PathBuf,
+
+    // Compute byte entropy and flag likely encrypted or compressed files
+    #[clap(long, help = "Flag high entropy files without a known magic number")]
+    entropy: bool,
+
+    // Also compute entropy per block of this many bytes
+    #[clap(
+        long,
+        requires = "entropy",
+        value_name = "BYTES",
+        value_parser = entropy::parse_block_size,
+        help = "Compute entropy per block of BYTES, at least 4096 so that random blocks reach the threshold"
+    )]
+    block_size: Option<usize>,
+
+    // Entropy in bits per byte from which data is considered random
+    #[clap(
+        long,
+        default_value_t = 7.5,
+        help = "Entropy threshold in bits per byte"
+    )]
+    entropy_threshold: f64,
+
+    // Report line endings, BOMs, trailing whitespace and indentation of text files
+    #[clap(
+        long,
+        help = "Report line endings, BOM and whitespace issues of text files"
+    )]
+    text_report: bool,
+
+    // Rewrite text files with consistent line endings and no trailing whitespace
+    #[clap(long, help = "Normalize text files in place")]
+    normalize: bool,
+
+    // Line ending written when normalizing
+    #[clap(
+        long,
+        value_enum,
+        default_value = "lf",
+        help = "Line ending to normalize to"
+    )]
+    eol: text::LineEnding,
+
+    // Cap the number of directories listed per second
+    #[clap(
+        long,
+        value_name = "N",
+        value_parser = throttle::parse_rate,
+        help = "Maximum directories read per second"
+    )]
+    max_dirs_per_sec: Option<f64>,
+
+    // Cap the number of file bytes read per second, e.g. 10M
+    #[clap(
+        long,
+        value_name = "BYTES",
+        value_parser = throttle::parse_size,
+        help = "Maximum bytes read per second (K, M and G suffixes allowed)"
+    )]
+    max_bytes_per_sec: Option<u64>,
+
+    // Lower the I/O priority of the crawl
+    #[clap(long, value_enum, help = "Lower the I/O priority with ioprio_set")]
+    ioprio: Option<IoPriority>,
+
+    // Print statistics grouped by content type and by extension after the crawl
+    #[clap(long, help = "Print per content type and per extension statistics")]
+    summary: bool,
+}
 
 /// Recursively crawls the directory at the given path, parses the file
 /// and prints whether it is a plain text file or not.
@@ -9,53 +99,184 @@ use std::path::Path;
 /// # Arguments
 ///
 /// * `path` - A reference to a `Path` that represents the directory to crawl.
+/// * `opts` - The command-line options selecting the extra analyses.
+/// * `throttle` - The rate limits applied to directory listings and file reads.
+/// * `summary` - Collects the statistics of every classified file.
+///
 /// e.g. cargo run /path/to/directory
-
-fn crawl(path: &Path) -> Result<()> {
+fn crawl(path: &Path, opts: &Opts, throttle: &Throttle, summary: &mut Summary) -> Result<()> {
     // Read the directory entries
+    throttle.read_dir();
     for entry in fs::read_dir(path)? {
+        if interrupt::interrupted() {
+            break;
+        }
         let entry = entry?;
-        println!("Entry path: {}", entry.path().display());
+        println!("Entry path: {}", escaped(&entry.path()));
         let path = entry.path();
         if path.is_dir() {
             // Recursively crawl subdirectories
-            crawl(&path)?;
+            crawl(&path, opts, throttle, summary)?;
         } else if path.is_file() {
             let file = fs::File::open(&path)?;
-            let mut buffer = [0; 1024];
-            let mut reader = BufReader::new(file);
-            let bytes_read = reader.read(&mut buffer)?;
-
-            if bytes_read > 0 && std::str::from_utf8(&buffer[..bytes_read]).is_ok() {
-                println!("Text file: {}", path.display());
-            } else {
-                println!("Other file: {}", path.display());
-            }
+            let metadata = file.metadata()?;
+            let content_type = classify(&path, file, opts, throttle)?;
+            summary.add(&path, content_type, &metadata);
         }
     }
     Ok(())
 }
 
-fn main() {
-    // Collect command-line arguments
-    let args: Vec<String> = env::args().collect();
+/// Prints whether the file is an ELF binary, a plain text file or something
+/// else, followed by the details of any analysis enabled in the options.
+///
+/// # Arguments
+///
+/// * `path` - A reference to a `Path` that represents the file to classify.
+/// * `file` - The file opened for reading.
+/// * `opts` - The command-line options selecting the extra analyses.
+/// * `throttle` - The rate limits applied to file reads.
+///
+/// # Returns
+///
+/// The detected content type: a known format such as "ELF" or "gzip",
+/// "text", "empty" or "data".
+fn classify(path: &Path, file: fs::File, opts: &Opts, throttle: &Throttle) -> Result<&'static str> {
+    let mut buffer = [0; 1024];
+    let mut reader = BufReader::new(throttle.reader(file));
+    let bytes_read = reader.read(&mut buffer)?;
+    let header = &buffer[..bytes_read];
+    let is_text = bytes_read > 0 && std::str::from_utf8(header).is_ok();
+    let format = magic::identify(header);
 
-    // Check if the user provided a path argument
-    if args.len() < 2 {
-        eprintln!("Usage: {} <path>", args[0]);
-        return;
+    if header.starts_with(elf::MAGIC) {
+        // Executables and libraries get their ELF header inspected, only
+        // the headers are read so this bypasses the throttle
+        println!("ELF file: {}", escaped(path));
+        match elf::inspect(reader.get_ref().get_ref()) {
+            Ok(info) => println!("{info}"),
+            Err(e) => eprintln!("Failed to parse ELF header of {}: {}", escaped(path), e),
+        }
+    } else if is_text {
+        println!("Text file: {}", escaped(path));
+        if opts.text_report || opts.normalize {
+            text_hygiene(path, &mut reader, format, opts)?;
+        }
+    } else {
+        println!("Other file: {}", escaped(path));
+    }
+
+    if opts.entropy {
+        reader.rewind()?;
+        let report = entropy::analyze(&mut reader, opts.block_size)?;
+        println!("    Entropy: {:.2} bits/byte", report.overall);
+
+        let mut flagged = report.overall >= opts.entropy_threshold;
+        if opts.block_size.is_some() {
+            let (high, max) = report.high_blocks(opts.entropy_threshold);
+            println!(
+                "    Blocks: {high}/{} at or above {:.2} bits/byte (max {max:.2})",
+                report.blocks.len(),
+                opts.entropy_threshold
+            );
+            flagged |= high > 0;
+        }
+
+        // Random looking data is only expected from formats we recognise
+        match format {
+            Some(name) => println!("    Format: {name}"),
+            None if flagged => println!(
+                "    Warning: high entropy without a known magic number, possibly encrypted or compressed"
+            ),
+            None => {}
+        }
+    }
+
+    // Known magic numbers take precedence, some formats start with text
+    Ok(match format {
+        Some(name) => name,
+        None if bytes_read == 0 => "empty",
+        None if is_text => "text",
+        None => "data",
+    })
+}
+
+// Reports and optionally fixes the text hygiene of a file classified as text.
+// Only the first KiB was checked to classify it, so the whole file is checked
+// again before rewriting it, and files in a known format are never rewritten.
+fn text_hygiene(
+    path: &Path,
+    reader: &mut (impl Read + Seek),
+    format: Option<&str>,
+    opts: &Opts,
+) -> Result<()> {
+    let mut contents = Vec::new();
+    reader.rewind()?;
+    reader.read_to_end(&mut contents)?;
+
+    if opts.text_report {
+        text::analyze(&contents).print();
     }
+    if !opts.normalize {
+        return Ok(());
+    }
+    if let Some(name) = format {
+        println!("    Not normalized: {name} file");
+    } else if !text::is_text(&contents) {
+        println!("    Not normalized: binary content past the first KiB");
+    } else if fs::symlink_metadata(path)?.file_type().is_symlink() {
+        println!("    Not normalized: symbolic link");
+    } else if text::normalize_in_place(path, &contents, opts.eol)? {
+        println!("    Normalized: {}", escaped(path));
+    }
+    Ok(())
+}
 
-    let path = Path::new(&args[1]);
+fn main() {
+    // Parse command-line arguments
+    let opts = Opts::parse();
+    // Blocks too small for random data to reach the threshold would never be flagged
+    if let Some(size) = opts.block_size {
+        if entropy::random_entropy(size) < opts.entropy_threshold {
+            Opts::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "random data only scores about {:.2} bits/byte in blocks of {size} bytes, below the entropy threshold of {}; use larger blocks",
+                        entropy::random_entropy(size),
+                        opts.entropy_threshold
+                    ),
+                )
+                .exit();
+        }
+    }
+    if let Err(e) = interrupt::install() {
+        eprintln!("Failed to install signal handlers: {}", e);
+    }
+    let path = opts.path.as_path();
 
     // Check if the provided path exists
     if !path.exists() {
-        eprintln!("{} does not exist", path.display());
+        eprintln!("{} does not exist", escaped(path));
         return;
     }
 
+    // Lower the I/O priority before touching the disk
+    if let Some(priority) = opts.ioprio {
+        if let Err(e) = throttle::set_io_priority(priority) {
+            eprintln!("Failed to set I/O priority: {}", e);
+        }
+    }
+    let throttle = Throttle::new(opts.max_dirs_per_sec, opts.max_bytes_per_sec);
+
     // Attempt to crawl the directory and handle any errors
-    if let Err(e) = crawl(&path) {
-        eprintln!("Error while crawling {}: {}", path.display(), e);
+    let mut summary = Summary::default();
+    if let Err(e) = crawl(path, &opts, &throttle, &mut summary) {
+        eprintln!("Error while crawling {}: {}", escaped(path), e);
+    }
+    // The summary covers whatever was crawled, even after an interruption
+    if opts.summary {
+        summary.print();
     }
+    interrupt::exit_if_interrupted();
 }
@@ -0,0 +1,122 @@
+use crate::escape::escaped;
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Aggregated statistics for one content type or extension.
+#[derive(Default)]
+struct Group {
+    files: u64,
+    bytes: u64,
+    largest: Option<(u64, PathBuf)>,
+    newest: Option<SystemTime>,
+}
+
+impl Group {
+    fn add(&mut self, path: &Path, metadata: &Metadata) {
+        let size = metadata.len();
+        self.files += 1;
+        self.bytes += size;
+        if self
+            .largest
+            .as_ref()
+            .is_none_or(|(largest, _)| size > *largest)
+        {
+            self.largest = Some((size, path.to_path_buf()));
+        }
+        if let Ok(modified) = metadata.modified() {
+            self.newest = self.newest.max(Some(modified));
+        }
+    }
+}
+
+/// Per content type and per extension statistics collected during a crawl.
+#[derive(Default)]
+pub struct Summary {
+    by_type: HashMap<String, Group>,
+    by_extension: HashMap<String, Group>,
+}
+
+impl Summary {
+    /// Records a classified file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    /// * `content_type` - The content type detected by the classifier.
+    /// * `metadata` - The metadata of the file, for its size and modification time.
+    pub fn add(&mut self, path: &Path, content_type: &str, metadata: &Metadata) {
+        self.by_type
+            .entry(content_type.to_string())
+            .or_default()
+            .add(path, metadata);
+
+        // Extensions are case insensitive, "LOG" and "log" are one group
+        let extension = match path.extension() {
+            Some(ext) => escaped(Path::new(ext)).to_string().to_lowercase(),
+            None => "(none)".to_string(),
+        };
+        self.by_extension
+            .entry(extension)
+            .or_default()
+            .add(path, metadata);
+    }
+
+    /// Prints one table per grouping, biggest groups first.
+    pub fn print(&self) {
+        print_groups("Content type", &self.by_type);
+        print_groups("Extension", &self.by_extension);
+    }
+}
+
+// Prints a table of groups sorted by total size.
+fn print_groups(title: &str, groups: &HashMap<String, Group>) {
+    let mut groups: Vec<_> = groups.iter().collect();
+    groups.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then(a.0.cmp(b.0)));
+
+    println!();
+    println!(
+        "{:<16} {:>8} {:>10}  {:<19}  Largest file",
+        title, "Files", "Size", "Newest"
+    );
+    for (name, group) in groups {
+        let newest = match group.newest {
+            Some(time) => DateTime::<Local>::from(time)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            None => "-".to_string(),
+        };
+        let largest = match &group.largest {
+            Some((size, path)) => format!("{} ({})", escaped(path), human_size(*size)),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:<16} {:>8} {:>10}  {:<19}  {}",
+            name,
+            group.files,
+            human_size(group.bytes),
+            newest,
+            largest
+        );
+    }
+}
+
+// Formats a byte count with binary units, e.g. 1.5 MiB.
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
@@ -0,0 +1,324 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Result, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Attempts at finding an unused temporary file name
+const TEMP_ATTEMPTS: u32 = 100;
+
+// Distinguishes the temporary files of one run
+static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// The UTF-8 byte order mark.
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Line ending to write when normalizing a text file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+        }
+    }
+}
+
+/// Hygiene findings for a single text file.
+#[derive(Debug, Default)]
+pub struct TextReport {
+    pub bom: bool,
+    pub lf_lines: usize,
+    pub crlf_lines: usize,
+    pub missing_final_newline: bool,
+    pub trailing_whitespace: usize,
+    pub tab_indented: usize,
+    pub space_indented: usize,
+    pub mixed_indented: usize,
+}
+
+impl TextReport {
+    /// Returns the line ending style: LF, CRLF, mixed or none for single line files.
+    pub fn line_endings(&self) -> &'static str {
+        match (self.lf_lines, self.crlf_lines) {
+            (0, 0) => "none",
+            (_, 0) => "LF",
+            (0, _) => "CRLF",
+            _ => "mixed",
+        }
+    }
+
+    /// Returns the indentation style: tabs, spaces, mixed or none.
+    pub fn indentation(&self) -> &'static str {
+        match (self.tab_indented, self.space_indented, self.mixed_indented) {
+            (0, 0, 0) => "none",
+            (_, 0, 0) => "tabs",
+            (0, _, 0) => "spaces",
+            _ => "mixed",
+        }
+    }
+
+    /// Prints the findings indented under the classification line.
+    pub fn print(&self) {
+        println!("    Line endings: {}", self.line_endings());
+        println!("    BOM: {}", if self.bom { "yes" } else { "no" });
+        println!(
+            "    Final newline: {}",
+            if self.missing_final_newline {
+                "missing"
+            } else {
+                "yes"
+            }
+        );
+        println!(
+            "    Trailing whitespace: {} lines",
+            self.trailing_whitespace
+        );
+        println!(
+            "    Indentation: {} ({} tab, {} space, {} mixed)",
+            self.indentation(),
+            self.tab_indented,
+            self.space_indented,
+            self.mixed_indented
+        );
+    }
+}
+
+/// Returns whether the contents are entirely valid UTF-8 without NUL bytes,
+/// so that normalizing them cannot corrupt binary data.
+///
+/// # Arguments
+///
+/// * `contents` - The full contents of the file.
+pub fn is_text(contents: &[u8]) -> bool {
+    !contents.contains(&0) && std::str::from_utf8(contents).is_ok()
+}
+
+/// Analyses the line endings, BOM, final newline, trailing whitespace and
+/// indentation of a text file's contents.
+///
+/// # Arguments
+///
+/// * `contents` - The full contents of the file.
+pub fn analyze(contents: &[u8]) -> TextReport {
+    let mut report = TextReport {
+        bom: contents.starts_with(BOM),
+        missing_final_newline: !contents.is_empty() && !contents.ends_with(b"\n"),
+        ..Default::default()
+    };
+
+    for line in contents.split_inclusive(|&b| b == b'\n') {
+        // Count and strip the line ending
+        let line = match line.strip_suffix(b"\n") {
+            Some(line) => match line.strip_suffix(b"\r") {
+                Some(line) => {
+                    report.crlf_lines += 1;
+                    line
+                }
+                None => {
+                    report.lf_lines += 1;
+                    line
+                }
+            },
+            None => line,
+        };
+
+        if line.ends_with(b" ") || line.ends_with(b"\t") {
+            report.trailing_whitespace += 1;
+        }
+
+        // Whitespace only lines carry no indentation
+        let indent_len = line
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+        if indent_len == line.len() {
+            continue;
+        }
+        let indent = &line[..indent_len];
+        match (indent.contains(&b'\t'), indent.contains(&b' ')) {
+            (true, true) => report.mixed_indented += 1,
+            (true, false) => report.tab_indented += 1,
+            (false, true) => report.space_indented += 1,
+            (false, false) => {}
+        }
+    }
+    report
+}
+
+/// Returns the contents with the BOM removed, every line ending converted,
+/// trailing whitespace stripped and a final newline added.
+///
+/// Indentation is left alone since the right tab width cannot be guessed.
+///
+/// # Arguments
+///
+/// * `contents` - The full contents of the file.
+/// * `eol` - The line ending to use for every line.
+pub fn normalize(contents: &[u8], eol: LineEnding) -> Vec<u8> {
+    let contents = contents.strip_prefix(BOM).unwrap_or(contents);
+    let mut output = Vec::with_capacity(contents.len());
+    if contents.is_empty() {
+        return output;
+    }
+
+    for line in contents.split_inclusive(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let end = line
+            .iter()
+            .rposition(|&b| b != b' ' && b != b'\t')
+            .map_or(0, |i| i + 1);
+        output.extend_from_slice(&line[..end]);
+        output.extend_from_slice(eol.bytes());
+    }
+    output
+}
+
+/// Rewrites the file in place with normalized contents, if anything changes.
+///
+/// The new contents are written to a temporary file next to the original,
+/// which then replaces it, so an interrupted run never leaves a truncated file.
+/// The temporary file gets a new name and is created exclusively, so an
+/// existing file or symlink of that name is never written through, and it is
+/// removed if anything fails.
+///
+/// # Arguments
+///
+/// * `path` - The text file to rewrite.
+/// * `contents` - The current contents of the file.
+/// * `eol` - The line ending to use for every line.
+///
+/// # Returns
+///
+/// Whether the file was modified.
+pub fn normalize_in_place(path: &Path, contents: &[u8], eol: LineEnding) -> Result<bool> {
+    let normalized = normalize(contents, eol);
+    if normalized == contents {
+        return Ok(false);
+    }
+
+    let (temp_path, mut temp) = create_temp(path)?;
+    let result = (|| {
+        temp.write_all(&normalized)?;
+        // Keep the original permissions, executable scripts must stay executable
+        temp.set_permissions(fs::metadata(path)?.permissions())?;
+        temp.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result.map(|_| true)
+}
+
+// Creates a new temporary file next to the given one, failing rather than
+// opening a file or following a symlink that already exists.
+fn create_temp(path: &Path) -> Result<(PathBuf, File)> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    for _ in 0..TEMP_ATTEMPTS {
+        let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path =
+            path.with_file_name(format!(".{name}.{}.{count}.normalize.tmp", process::id()));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(ErrorKind::AlreadyExists.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyze_counts_line_endings() {
+        let report = analyze(b"a\r\nb\r\n");
+        assert_eq!((report.lf_lines, report.crlf_lines), (0, 2));
+        assert_eq!(report.line_endings(), "CRLF");
+
+        let report = analyze(b"a\nb\r\nc");
+        assert_eq!((report.lf_lines, report.crlf_lines), (1, 1));
+        assert_eq!(report.line_endings(), "mixed");
+        assert!(report.missing_final_newline);
+    }
+
+    #[test]
+    fn analyze_finds_bom_whitespace_and_indentation() {
+        let report = analyze(b"\xef\xbb\xbfa \n\tb\n  c\n \td\n");
+        assert!(report.bom);
+        assert!(!report.missing_final_newline);
+        assert_eq!(report.trailing_whitespace, 1);
+        assert_eq!(
+            (
+                report.tab_indented,
+                report.space_indented,
+                report.mixed_indented
+            ),
+            (1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn normalize_converts_crlf() {
+        assert_eq!(normalize(b"a\r\nb\r\n", LineEnding::Lf), b"a\nb\n");
+        assert_eq!(normalize(b"a\nb", LineEnding::Crlf), b"a\r\nb\r\n");
+    }
+
+    #[test]
+    fn normalize_fixes_mixed_endings_and_whitespace() {
+        assert_eq!(normalize(b"a \r\nb\t\nc", LineEnding::Lf), b"a\nb\nc\n");
+        // Indentation is kept
+        assert_eq!(normalize(b"\tx\n", LineEnding::Lf), b"\tx\n");
+    }
+
+    #[test]
+    fn normalize_strips_bom() {
+        assert_eq!(normalize(b"\xef\xbb\xbfa\n", LineEnding::Lf), b"a\n");
+        assert_eq!(normalize(b"\xef\xbb\xbf", LineEnding::Lf), b"");
+    }
+
+    #[test]
+    fn normalize_in_place_does_not_follow_planted_symlinks() {
+        let dir = std::env::temp_dir().join(format!("normalize-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("crlf.txt");
+        let target = dir.join("target");
+        fs::write(&path, b"a\r\n").unwrap();
+        fs::write(&target, b"kept").unwrap();
+        // Links to another file under the temporary names tried first
+        for count in 0..5 {
+            let name = format!(".crlf.txt.{}.{count}.normalize.tmp", process::id());
+            std::os::unix::fs::symlink(&target, dir.join(name)).unwrap();
+        }
+
+        assert!(normalize_in_place(&path, b"a\r\n", LineEnding::Lf).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), b"a\n");
+        assert_eq!(fs::read(&target).unwrap(), b"kept");
+        // No temporary file is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 7);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn binary_contents_are_not_text() {
+        assert!(is_text(b"plain\r\ntext\n"));
+        assert!(!is_text(b"text\0with a NUL"));
+        let mut contents = b"valid UTF-8 header\n".repeat(100);
+        contents.extend_from_slice(&[0xff, 0xfe, 0x80]);
+        assert!(!is_text(&contents));
+    }
+}
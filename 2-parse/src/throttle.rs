@@ -0,0 +1,175 @@
+use std::cell::Cell;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// I/O scheduling priority to switch the process to before crawling.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum IoPriority {
+    /// Only get disk time when no other process needs it.
+    Idle,
+    /// Lowest level of the default best-effort class.
+    Low,
+}
+
+// ioprio_set(2) constants, see linux/ioprio.h
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+const IOPRIO_CLASS_BE: libc::c_int = 2;
+const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+/// Lowers the I/O priority of the current process with `ioprio_set`, so the
+/// crawl yields the disk to production workloads.
+///
+/// # Arguments
+///
+/// * `priority` - The priority class to switch to.
+pub fn set_io_priority(priority: IoPriority) -> io::Result<()> {
+    let value = match priority {
+        IoPriority::Idle => IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        IoPriority::Low => (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | 7,
+    };
+    // A `who` of 0 means the calling process
+    let result = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, value) };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Parses a byte count with an optional K, M or G suffix (powers of 1024).
+///
+/// # Arguments
+///
+/// * `value` - A string such as "512", "64K" or "10M".
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&value[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .filter(|&n| n > 0)
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size: {value}"))
+}
+
+/// Parses a rate per second, a finite number greater than 0.
+///
+/// # Arguments
+///
+/// * `value` - A string such as "50" or "0.5".
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|&rate| rate.is_finite() && rate > 0.0)
+        .ok_or_else(|| format!("invalid rate: {value}, expected a number greater than 0"))
+}
+
+// Token bucket allowing `rate` units per second with bursts of up to one second.
+struct RateLimiter {
+    rate: f64,
+    tokens: Cell<f64>,
+    last: Cell<Instant>,
+}
+
+impl RateLimiter {
+    fn new(rate: f64) -> Self {
+        RateLimiter {
+            rate,
+            tokens: Cell::new(rate),
+            last: Cell::new(Instant::now()),
+        }
+    }
+
+    // Takes `amount` tokens, sleeping until the bucket has refilled enough.
+    fn acquire(&self, amount: u64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last.get()).as_secs_f64();
+        let tokens = (self.tokens.get() + elapsed * self.rate).min(self.rate) - amount as f64;
+        self.last.set(now);
+        self.tokens.set(tokens);
+
+        if tokens < 0.0 {
+            // Pay the debt by waiting, the bucket is then empty
+            let wait = Duration::try_from_secs_f64(-tokens / self.rate).unwrap_or(Duration::MAX);
+            thread::sleep(wait);
+            self.last.set(now + wait);
+            self.tokens.set(0.0);
+        }
+    }
+}
+
+/// Rate limits for a crawl: directories listed and bytes read per second.
+///
+/// Uses interior mutability so it can be shared by reference across the
+/// recursive crawl and the readers it hands out.
+pub struct Throttle {
+    dirs: Option<RateLimiter>,
+    bytes: Option<RateLimiter>,
+}
+
+impl Throttle {
+    /// Creates a new `Throttle`, a `None` limit leaves that kind of I/O unthrottled.
+    ///
+    /// # Arguments
+    ///
+    /// * `dirs_per_sec` - Maximum number of directories listed per second.
+    /// * `bytes_per_sec` - Maximum number of file bytes read per second.
+    pub fn new(dirs_per_sec: Option<f64>, bytes_per_sec: Option<u64>) -> Self {
+        Throttle {
+            dirs: dirs_per_sec.map(RateLimiter::new),
+            bytes: bytes_per_sec.map(|rate| RateLimiter::new(rate as f64)),
+        }
+    }
+
+    /// Waits until another directory may be listed.
+    pub fn read_dir(&self) {
+        if let Some(limiter) = &self.dirs {
+            limiter.acquire(1);
+        }
+    }
+
+    /// Wraps a reader so every byte read from it counts against the byte limit.
+    pub fn reader<R>(&self, inner: R) -> ThrottledReader<'_, R> {
+        ThrottledReader {
+            inner,
+            throttle: self,
+        }
+    }
+}
+
+/// A reader whose throughput is capped by a `Throttle`.
+pub struct ThrottledReader<'a, R> {
+    inner: R,
+    throttle: &'a Throttle,
+}
+
+impl<R> ThrottledReader<'_, R> {
+    /// Returns the wrapped reader, reads through it bypass the throttle.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        if let Some(limiter) = &self.throttle.bytes {
+            limiter.acquire(bytes_read as u64);
+        }
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Seek> Seek for ThrottledReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,176 @@
+use crate::bucket::{Bucket, Series};
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+// Smallest spread of the baseline, in errors, so that a few errors after a
+// quiet period with no spread at all do not get an infinite score
+const MIN_SPREAD: f64 = 1.0;
+
+// Scales the median absolute deviation to the standard deviation of a normal
+// distribution, so z-score thresholds mean the same for every baseline
+const MAD_SCALE: f64 = 1.4826;
+
+/// How the expected error count of a bucket is computed from the buckets
+/// before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Baseline {
+    /// Mean and standard deviation of the previous buckets in the window.
+    Mean,
+    /// Exponentially weighted moving average and deviation of all previous
+    /// buckets, with a span of the window.
+    Ewma,
+    /// Median and median absolute deviation of the previous buckets in the
+    /// window, which a few earlier spikes do not inflate.
+    Median,
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Baseline::Mean => "mean",
+            Baseline::Ewma => "ewma",
+            Baseline::Median => "median",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A bucket with more errors than its baseline allows.
+pub struct Anomaly {
+    pub start: DateTime<Utc>,
+    pub bucket: Bucket,
+    /// The error count expected from the baseline.
+    pub expected: f64,
+    /// How many deviations of the baseline the error count is above it.
+    pub score: f64,
+}
+
+/// How anomalies are detected.
+#[derive(Clone, Copy, Debug)]
+pub struct Detector {
+    pub baseline: Baseline,
+    /// The number of previous buckets the baseline is computed from.
+    pub window: usize,
+    /// The score above which a bucket is reported.
+    pub z_score: f64,
+}
+
+impl Detector {
+    /// Finds the buckets whose error count is more than `z_score` deviations
+    /// above the baseline of the buckets before them. Only spikes are
+    /// reported, and only buckets with a full window of buckets before them
+    /// are scored.
+    ///
+    /// # Arguments
+    ///
+    /// * `series` - The counts of every bucket, including empty ones.
+    pub fn detect(&self, series: &Series) -> Vec<Anomaly> {
+        let buckets: Vec<(DateTime<Utc>, Bucket)> = series.iter().collect();
+        let errors: Vec<f64> = buckets.iter().map(|(_, b)| b.errors() as f64).collect();
+        let mut ewma = Ewma::new(self.window);
+        let mut anomalies = Vec::new();
+        for (i, (start, bucket)) in buckets.into_iter().enumerate() {
+            let history = &errors[i.saturating_sub(self.window)..i];
+            let (expected, spread) = match self.baseline {
+                Baseline::Mean => mean_deviation(history),
+                Baseline::Ewma => (ewma.mean, ewma.variance.sqrt()),
+                Baseline::Median => median_deviation(history),
+            };
+            ewma.add(errors[i]);
+            if i < self.window {
+                continue;
+            }
+            let score = (errors[i] - expected) / spread.max(MIN_SPREAD);
+            if score > self.z_score {
+                anomalies.push(Anomaly {
+                    start,
+                    bucket,
+                    expected,
+                    score,
+                });
+            }
+        }
+        anomalies
+    }
+}
+
+/// Parses the number of buckets of the baseline window given on the command
+/// line, at least 1.
+pub fn parse_window(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(window) if window > 0 => Ok(window),
+        _ => Err(format!(
+            "expected a number of buckets of at least 1, got '{value}'"
+        )),
+    }
+}
+
+/// Parses the z-score threshold given on the command line, a positive number.
+pub fn parse_z_score(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(score) if score > 0.0 && score.is_finite() => Ok(score),
+        _ => Err(format!("expected a positive number, got '{value}'")),
+    }
+}
+
+// Returns the mean and standard deviation of the values.
+fn mean_deviation(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    (mean, variance.sqrt())
+}
+
+// Returns the median and the scaled median absolute deviation of the values.
+fn median_deviation(values: &[f64]) -> (f64, f64) {
+    let middle = median(values.to_vec());
+    let deviations = values.iter().map(|v| (v - middle).abs()).collect();
+    (middle, median(deviations) * MAD_SCALE)
+}
+
+// Returns the middle value, or the mean of the two middle values.
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[middle - 1] + values[middle]) / 2.0,
+        _ => values[middle],
+    }
+}
+
+// Exponentially weighted moving average and variance, updated one value at a time.
+struct Ewma {
+    alpha: f64,
+    mean: f64,
+    variance: f64,
+    started: bool,
+}
+
+impl Ewma {
+    // A span of N values gives the most recent value a weight of 2 / (N + 1).
+    fn new(span: usize) -> Self {
+        Ewma {
+            alpha: 2.0 / (span as f64 + 1.0),
+            mean: 0.0,
+            variance: 0.0,
+            started: false,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        if !self.started {
+            self.mean = value;
+            self.started = true;
+            return;
+        }
+        let difference = value - self.mean;
+        let increment = self.alpha * difference;
+        self.mean += increment;
+        self.variance = (1.0 - self.alpha) * (self.variance + difference * increment);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::level::{Level, LevelCounts};
+use crate::zone::Zone;
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Width of the time buckets entries are aggregated into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum BucketSize {
+    #[value(name = "1m")]
+    OneMinute,
+    #[value(name = "5m")]
+    FiveMinutes,
+    #[value(name = "15m")]
+    FifteenMinutes,
+    #[value(name = "1h")]
+    OneHour,
+    #[value(name = "1d")]
+    OneDay,
+}
+
+impl BucketSize {
+    /// Returns the width of the bucket.
+    pub fn duration(self) -> TimeDelta {
+        match self {
+            BucketSize::OneMinute => TimeDelta::minutes(1),
+            BucketSize::FiveMinutes => TimeDelta::minutes(5),
+            BucketSize::FifteenMinutes => TimeDelta::minutes(15),
+            BucketSize::OneHour => TimeDelta::hours(1),
+            BucketSize::OneDay => TimeDelta::days(1),
+        }
+    }
+
+    /// Returns the start of the bucket containing the timestamp.
+    ///
+    /// Every size divides a day, so buckets are aligned on midnight: 15 minute
+    /// buckets start at :00, :15, :30 and :45 of every hour.
+    pub fn floor(self, timestamp: NaiveDateTime) -> NaiveDateTime {
+        let size = self.duration().num_seconds();
+        let seconds = timestamp.num_seconds_from_midnight() as i64;
+        let midnight = timestamp.date().and_time(NaiveTime::MIN);
+        midnight + TimeDelta::seconds(seconds - seconds % size)
+    }
+}
+
+impl fmt::Display for BucketSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BucketSize::OneMinute => "1m",
+            BucketSize::FiveMinutes => "5m",
+            BucketSize::FifteenMinutes => "15m",
+            BucketSize::OneHour => "1h",
+            BucketSize::OneDay => "1d",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Counts for the log entries of one time bucket.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bucket {
+    pub total: u64,
+    pub levels: LevelCounts,
+}
+
+impl Bucket {
+    /// Counts one entry, with its level when it has one.
+    pub fn add(&mut self, level: Option<Level>) {
+        self.total += 1;
+        if let Some(level) = level {
+            self.levels.add(level);
+        }
+    }
+
+    /// Adds the counts of another bucket to this one.
+    pub fn merge(&mut self, other: &Bucket) {
+        self.total += other.total;
+        self.levels.merge(&other.levels);
+    }
+
+    /// Returns the number of ERROR and FATAL entries.
+    pub fn errors(&self) -> u64 {
+        self.levels.get(Level::Error) + self.levels.get(Level::Fatal)
+    }
+
+    /// Returns the percentage of entries that are errors, 0 for an empty bucket.
+    pub fn error_rate(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.errors() as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Entries: {}, Errors: {} ({:.2}%) - {}",
+            self.total,
+            self.errors(),
+            self.error_rate(),
+            self.levels
+        )
+    }
+}
+
+/// Buckets keyed by the instant they start at, kept in time order.
+///
+/// Entries are aggregated in a map rather than as a running count, so lines
+/// that are out of order still land in their own bucket and the last bucket
+/// is never lost.
+///
+/// Buckets are aligned on the wall clock of the zone but keyed on instants,
+/// so the hour repeated when clocks go back gives two buckets and the hour
+/// skipped when they go forward gives none.
+#[derive(Debug)]
+pub struct Series {
+    size: BucketSize,
+    zone: Zone,
+    buckets: BTreeMap<DateTime<Utc>, Bucket>,
+}
+
+impl Series {
+    /// Creates a new, empty `Series`.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The width of each bucket.
+    /// * `zone` - The time zone buckets are aligned in.
+    pub fn new(size: BucketSize, zone: Zone) -> Self {
+        Series {
+            size,
+            zone,
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the width of each bucket.
+    pub fn size(&self) -> BucketSize {
+        self.size
+    }
+
+    /// Returns the time zone buckets are aligned in.
+    pub fn zone(&self) -> Zone {
+        self.zone
+    }
+
+    /// Counts an entry in the bucket containing its timestamp and returns the
+    /// start of that bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - The time of the entry.
+    /// * `level` - The level of the entry, if it has one.
+    pub fn add(&mut self, timestamp: DateTime<Utc>, level: Option<Level>) -> DateTime<Utc> {
+        let start = self.floor(timestamp);
+        self.buckets.entry(start).or_default().add(level);
+        start
+    }
+
+    /// Returns the bucket starting at the given time, empty if it has no entries.
+    pub fn get(&self, start: DateTime<Utc>) -> Bucket {
+        self.buckets.get(&start).copied().unwrap_or_default()
+    }
+
+    /// Returns the start of the bucket after the one starting at `start`.
+    ///
+    /// Days are 23 or 25 hours long when clocks change, so the next bucket is
+    /// looked for rather than assumed to be one bucket width later.
+    pub fn end(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        let mut candidate = start + self.size.duration();
+        loop {
+            let next = self.floor(candidate);
+            if next > start {
+                return next;
+            }
+            candidate += TimeDelta::hours(1);
+        }
+    }
+
+    /// Returns every bucket from the first to the last entry in time order,
+    /// with their start times. Buckets without entries are included with zero
+    /// counts, so the result is a continuous time series.
+    pub fn iter(&self) -> impl Iterator<Item = (DateTime<Utc>, Bucket)> + '_ {
+        let first = self.buckets.keys().next().copied();
+        let last = self.buckets.keys().next_back().copied();
+        std::iter::successors(first, move |&start| Some(self.end(start)))
+            .take_while(move |&start| Some(start) <= last)
+            .map(|start| (start, self.get(start)))
+    }
+
+    /// Returns the number of buckets from the first to the last entry.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns the sum of every bucket.
+    pub fn total(&self) -> Bucket {
+        let mut total = Bucket::default();
+        for bucket in self.buckets.values() {
+            total.merge(bucket);
+        }
+        total
+    }
+    // Returns the start of the bucket containing an instant: the wall clock
+    // time is floored, then taken back by as much as it moved, so the offset
+    // in use at the instant is kept. When the offset changed since the start
+    // of the bucket, as for day buckets, the start is looked up in the zone.
+    fn floor(&self, instant: DateTime<Utc>) -> DateTime<Utc> {
+        let local = self.zone.local(instant);
+        let start = self.size.floor(local);
+        let candidate = instant - (local - start);
+        if self.zone.local(candidate) == start {
+            return candidate;
+        }
+        self.zone.instant(start).unwrap_or(candidate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instant(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().to_utc()
+    }
+
+    // Counts one entry at each instant and returns the start of every bucket
+    // with its count, the starts written in the zone
+    fn buckets(size: BucketSize, zone: &str, instants: &[&str]) -> Vec<(String, u64)> {
+        let zone: Zone = zone.parse().unwrap();
+        let mut series = Series::new(size, zone);
+        for text in instants {
+            series.add(instant(text), None);
+        }
+        series
+            .iter()
+            .map(|(start, bucket)| (zone.format_instant(start), bucket.total))
+            .collect()
+    }
+
+    #[test]
+    fn repeated_hour_when_clocks_go_back() {
+        // 02:10 twice in Paris, first at +02:00 then at +01:00
+        let hours = buckets(
+            BucketSize::OneHour,
+            "Europe/Paris",
+            &[
+                "2023-10-29T00:10:00Z",
+                "2023-10-29T01:10:00Z",
+                "2023-10-29T02:10:00Z",
+            ],
+        );
+        assert_eq!(
+            hours,
+            [
+                ("2023-10-29T02:00:00+02:00".to_string(), 1),
+                ("2023-10-29T02:00:00+01:00".to_string(), 1),
+                ("2023-10-29T03:00:00+01:00".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn skipped_hour_when_clocks_go_forward() {
+        // 01:59 is followed by 03:00 in Paris
+        let hours = buckets(
+            BucketSize::OneHour,
+            "Europe/Paris",
+            &["2023-03-26T00:30:00Z", "2023-03-26T02:30:00Z"],
+        );
+        assert_eq!(
+            hours,
+            [
+                ("2023-03-26T01:00:00+01:00".to_string(), 1),
+                ("2023-03-26T03:00:00+02:00".to_string(), 0),
+                ("2023-03-26T04:00:00+02:00".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn days_of_23_and_25_hours() {
+        let days = buckets(
+            BucketSize::OneDay,
+            "Europe/Paris",
+            &[
+                "2023-10-28T23:30:00Z",
+                "2023-10-29T22:30:00Z",
+                "2023-10-29T23:30:00Z",
+            ],
+        );
+        assert_eq!(
+            days,
+            [
+                ("2023-10-29T00:00:00+02:00".to_string(), 2),
+                ("2023-10-30T00:00:00+01:00".to_string(), 1),
+            ]
+        );
+
+        let zone: Zone = "Europe/Paris".parse().unwrap();
+        let series = Series::new(BucketSize::OneDay, zone);
+        let start = instant("2023-03-25T23:00:00Z");
+        assert_eq!(series.end(start) - start, TimeDelta::hours(23));
+        let start = instant("2023-10-28T22:00:00Z");
+        assert_eq!(series.end(start) - start, TimeDelta::hours(25));
+    }
+
+    #[test]
+    fn fixed_offsets_fill_the_gaps() {
+        let quarters = buckets(
+            BucketSize::FifteenMinutes,
+            "+05:30",
+            &["2024-01-01T00:05:00Z", "2024-01-01T00:50:00Z"],
+        );
+        assert_eq!(
+            quarters,
+            [
+                ("2024-01-01T05:30:00+05:30".to_string(), 1),
+                ("2024-01-01T05:45:00+05:30".to_string(), 0),
+                ("2024-01-01T06:00:00+05:30".to_string(), 0),
+                ("2024-01-01T06:15:00+05:30".to_string(), 1),
+            ]
+        );
+    }
+}
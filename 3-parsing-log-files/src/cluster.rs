@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+// Token standing for any value in a template
+const WILDCARD: &str = "<*>";
+
+// Variable parts of messages and what they are replaced with, applied in order
+static MASKS: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
+    [
+        (
+            r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b",
+            "<ID>",
+        ),
+        (r"\b\d{1,3}(?:\.\d{1,3}){3}(?::\d+)?\b", "<IP>"),
+        (r"\b0x[0-9a-fA-F]+\b|\b[0-9a-fA-F]{16,}\b", "<HEX>"),
+        // Only from the start of a word, so "km/h" is kept
+        (
+            r#"(^|[\s=:"'(\[])(?:[A-Za-z]:)?(?:/[\w.@%+~-]+)+/?"#,
+            "${1}<PATH>",
+        ),
+        (r"[-+]?\b\d+(?:\.\d+)?\b", "<NUM>"),
+    ]
+    .into_iter()
+    .map(|(pattern, mask)| (Regex::new(pattern).unwrap(), mask))
+    .collect()
+});
+
+// Replaces the variable parts of a message, such as numbers, IDs, paths and
+// IP addresses, with placeholders and splits it into tokens. Tokens still
+// holding digits after that, e.g. timestamps, become wildcards.
+fn tokenize(message: &str) -> Vec<String> {
+    let mut masked = message.to_string();
+    for (regex, mask) in MASKS.iter() {
+        masked = regex.replace_all(&masked, *mask).into_owned();
+    }
+    masked
+        .split_whitespace()
+        .map(|token| match token.bytes().any(|b| b.is_ascii_digit()) {
+            true => WILDCARD.to_string(),
+            false => token.to_string(),
+        })
+        .collect()
+}
+
+/// Messages sharing a template.
+#[derive(Debug)]
+pub struct Cluster {
+    template: Vec<String>,
+    pub count: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// The first message of the cluster, as logged, with its continuation
+    /// lines.
+    pub example: String,
+}
+
+impl Cluster {
+    /// Returns the template as text, e.g. `Disk space low: <NUM> MB left`.
+    pub fn template(&self) -> String {
+        self.template.join(" ")
+    }
+
+    // Returns the share of tokens equal to the template, and the number of
+    // wildcards, which are skipped.
+    fn similarity(&self, tokens: &[String]) -> (f64, usize) {
+        let mut equal = 0;
+        let mut wildcards = 0;
+        for (template, token) in self.template.iter().zip(tokens) {
+            if template == WILDCARD {
+                wildcards += 1;
+            } else if template == token {
+                equal += 1;
+            }
+        }
+        (equal as f64 / tokens.len() as f64, wildcards)
+    }
+}
+
+/// Groups messages into templates with a simplified Drain algorithm.
+///
+/// Messages are masked and split into tokens, then only compared with the
+/// templates having the same number of tokens and the same first token. The
+/// message joins the most similar template if enough of their tokens are
+/// equal, turning the tokens that differ into wildcards, otherwise it starts
+/// a new template.
+pub struct Clusters {
+    similarity: f64,
+    clusters: Vec<Cluster>,
+    // Clusters by number of tokens and first token
+    groups: HashMap<(usize, String), Vec<usize>>,
+}
+
+impl Clusters {
+    /// Creates a new, empty `Clusters`.
+    ///
+    /// # Arguments
+    ///
+    /// * `similarity` - The share of equal tokens, between 0 and 1, for a
+    ///   message to join a template.
+    pub fn new(similarity: f64) -> Self {
+        Clusters {
+            similarity,
+            clusters: Vec::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Adds a message to the template it matches, or to a new one. Only the
+    /// first line is compared, so stack traces of different depths stay in
+    /// the same template.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message, as logged.
+    /// * `timestamp` - The time of the entry.
+    pub fn add(&mut self, message: &str, timestamp: DateTime<Utc>) {
+        let tokens = tokenize(message.lines().next().unwrap_or_default());
+        if tokens.is_empty() {
+            return;
+        }
+        let first = match tokens[0].starts_with('<') {
+            true => WILDCARD.to_string(),
+            false => tokens[0].clone(),
+        };
+        let group = self.groups.entry((tokens.len(), first)).or_default();
+
+        // Most similar template, preferring the most specific one on a tie
+        let best = group
+            .iter()
+            .map(|&index| {
+                let (similarity, wildcards) = self.clusters[index].similarity(&tokens);
+                (index, similarity, wildcards)
+            })
+            .filter(|&(_, similarity, _)| similarity >= self.similarity)
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.2.cmp(&a.2)));
+
+        match best {
+            Some((index, _, _)) => {
+                let cluster = &mut self.clusters[index];
+                for (template, token) in cluster.template.iter_mut().zip(&tokens) {
+                    if template != token {
+                        *template = WILDCARD.to_string();
+                    }
+                }
+                cluster.count += 1;
+                cluster.first_seen = cluster.first_seen.min(timestamp);
+                cluster.last_seen = cluster.last_seen.max(timestamp);
+            }
+            None => {
+                group.push(self.clusters.len());
+                self.clusters.push(Cluster {
+                    template: tokens,
+                    count: 1,
+                    first_seen: timestamp,
+                    last_seen: timestamp,
+                    example: message.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Returns the clusters, most frequent first.
+    pub fn sorted(&self) -> Vec<&Cluster> {
+        let mut clusters: Vec<&Cluster> = self.clusters.iter().collect();
+        clusters.sort_by(|a, b| b.count.cmp(&a.count).then(a.first_seen.cmp(&b.first_seen)));
+        clusters
+    }
+}
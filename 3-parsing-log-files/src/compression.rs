@@ -0,0 +1,138 @@
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+// Magic numbers at the start of compressed data, longest first
+const MAGIC: [(&[u8], Compression); 6] = [
+    (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], Compression::Xz),
+    (&[0x28, 0xb5, 0x2f, 0xfd], Compression::Zstd),
+    (b"PK\x03\x04", Compression::Zip),
+    // An empty archive
+    (b"PK\x05\x06", Compression::Zip),
+    (b"BZh", Compression::Bzip2),
+    (&[0x1f, 0x8b], Compression::Gzip),
+];
+
+// Number of bytes needed to recognise every format
+const MAGIC_LEN: usize = 6;
+
+/// Compression formats log files can be read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+    /// A zip archive, whose members are read as separate log files.
+    Zip,
+}
+
+impl Compression {
+    /// Returns the compression of data from the magic number it starts with.
+    ///
+    /// # Arguments
+    ///
+    /// * `head` - The first bytes of the data.
+    pub fn sniff(head: &[u8]) -> Option<Self> {
+        MAGIC
+            .iter()
+            .find(|(magic, _)| head.starts_with(magic))
+            .map(|&(_, compression)| compression)
+    }
+
+    /// Wraps a reader so it returns the decompressed data.
+    ///
+    /// Files made of several concatenated compressed streams, as written by
+    /// `cat a.gz b.gz` or some log rotation tools, are read to the end rather
+    /// than stopping after the first stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The compressed data.
+    pub fn decoder<'a>(self, reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        let decoder: Box<dyn Read> =
+            match self {
+                Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+                Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+                Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+                Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+                Compression::Zip => return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "zip archives can only be read from a file, not from another archive or a pipe",
+                )),
+            };
+        Ok(decoder)
+    }
+}
+
+/// Returns the compression of a file from its first bytes, `None` if it is
+/// not compressed or cannot be read.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+pub fn sniff_file(path: &Path) -> Option<Compression> {
+    let mut head = Vec::with_capacity(MAGIC_LEN);
+    let file = File::open(path).ok()?;
+    file.take(MAGIC_LEN as u64).read_to_end(&mut head).ok()?;
+    Compression::sniff(&head)
+}
+
+/// Wraps a reader so it returns the decompressed data if it is compressed,
+/// whatever its name. The format is recognised from the first bytes, which
+/// are put back in front of the data.
+///
+/// # Arguments
+///
+/// * `reader` - The possibly compressed data, e.g. a file or standard input.
+pub fn decompress<'a>(mut reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    // Read the head in full, a pipe may return it in several pieces
+    let mut head = Vec::with_capacity(MAGIC_LEN);
+    reader
+        .by_ref()
+        .take(MAGIC_LEN as u64)
+        .read_to_end(&mut head)?;
+    let compression = Compression::sniff(&head);
+    let reader = Cursor::new(head).chain(reader);
+    match compression {
+        Some(compression) => compression.decoder(reader),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+/// Returns the names of the files in a zip archive, in archive order.
+/// Directories are left out.
+///
+/// # Arguments
+///
+/// * `path` - The path of the archive.
+pub fn zip_members(path: &Path) -> io::Result<Vec<String>> {
+    let archive = ZipArchive::new(File::open(path)?)?;
+    let members = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(String::from)
+        .collect();
+    Ok(members)
+}
+
+/// Reads a member of a zip archive into memory, decompressed.
+///
+/// Members borrow the archive they are read from, so they are read whole
+/// rather than streamed.
+///
+/// # Arguments
+///
+/// * `path` - The path of the archive.
+/// * `name` - The name of the member.
+pub fn read_zip_member(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut member = archive.by_name(name)?;
+    let mut data = Vec::with_capacity(member.size() as usize);
+    member.read_to_end(&mut data)?;
+    Ok(data)
+}
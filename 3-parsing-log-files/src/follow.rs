@@ -0,0 +1,176 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Most lines returned by one poll, so a large file is read in parts rather
+/// than all at once.
+pub const MAX_POLL_LINES: usize = 10_000;
+
+/// Tails a plain text log file like `tail -F`, returning the lines appended
+/// since the last poll.
+///
+/// The path is watched rather than the open file: when the file is rotated
+/// (replaced by a file with another inode) the rest of the old file is read
+/// before switching to the new one, and when it is truncated it is read again
+/// from the start. A file that does not exist yet is opened once it appears.
+pub struct Follower {
+    path: PathBuf,
+    reader: Option<BufReader<File>>,
+    // Device and inode of the open file
+    id: (u64, u64),
+    // Bytes read from the open file
+    offset: u64,
+    // Start of a line whose end has not been written yet
+    partial: Vec<u8>,
+}
+
+impl Follower {
+    /// Creates a new `Follower` reading the file from the start.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the log file.
+    pub fn new(path: &Path) -> Self {
+        let mut follower = Follower {
+            path: path.to_path_buf(),
+            reader: None,
+            id: (0, 0),
+            offset: 0,
+            partial: Vec::new(),
+        };
+        follower.reopen();
+        follower
+    }
+
+    /// Returns the complete lines appended to the file since the last poll,
+    /// without their line endings, at most `MAX_POLL_LINES` of them. The rest
+    /// is returned by the next polls, before a rotation or truncation is
+    /// looked for.
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.reader.is_none() {
+            self.reopen();
+        }
+        self.read_lines(&mut lines);
+        if lines.len() >= MAX_POLL_LINES {
+            return lines;
+        }
+
+        match fs::metadata(&self.path) {
+            Ok(metadata) if (metadata.dev(), metadata.ino()) != self.id => {
+                // Rotated: read what was appended to the old file between the
+                // read above and the rename, then its last line is complete
+                if self.reader.is_some() {
+                    self.read_lines(&mut lines);
+                    if lines.len() >= MAX_POLL_LINES {
+                        return lines;
+                    }
+                    eprintln!("{} was rotated, reopening", self.path.display());
+                }
+                take_line(&mut self.partial, &mut lines);
+                self.reopen();
+                self.read_lines(&mut lines);
+            }
+            Ok(metadata) if metadata.len() < self.offset => {
+                eprintln!(
+                    "{} was truncated, reading from the start",
+                    self.path.display()
+                );
+                self.partial.clear();
+                self.offset = 0;
+                if let Some(reader) = self.reader.as_mut() {
+                    if let Err(e) = reader.seek(SeekFrom::Start(0)) {
+                        eprintln!("Error rewinding {}: {}", self.path.display(), e);
+                    }
+                }
+                self.read_lines(&mut lines);
+            }
+            _ => {}
+        }
+        lines
+    }
+
+    // Opens the file currently at the path, if any.
+    fn reopen(&mut self) {
+        self.reader = None;
+        self.id = (0, 0);
+        self.offset = 0;
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        match file.metadata() {
+            Ok(metadata) => self.id = (metadata.dev(), metadata.ino()),
+            Err(e) => {
+                eprintln!("Error reading {}: {}", self.path.display(), e);
+                return;
+            }
+        }
+        self.reader = Some(BufReader::new(file));
+    }
+
+    // Reads the complete lines available in the open file, up to
+    // `MAX_POLL_LINES` in all, keeping an unfinished last line for the next poll.
+    fn read_lines(&mut self, lines: &mut Vec<String>) {
+        let Some(reader) = self.reader.as_mut() else {
+            return;
+        };
+        while lines.len() < MAX_POLL_LINES {
+            match reader.read_until(b'\n', &mut self.partial) {
+                Ok(0) => break,
+                Ok(read) => {
+                    self.offset += read as u64;
+                    if self.partial.ends_with(b"\n") {
+                        take_line(&mut self.partial, lines);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading {}: {}", self.path.display(), e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Moves the buffered line, if any, to the lines read.
+fn take_line(partial: &mut Vec<u8>, lines: &mut Vec<String>) {
+    if partial.is_empty() {
+        return;
+    }
+    let mut line = String::from_utf8_lossy(partial).into_owned();
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    lines.push(line);
+    partial.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_files_are_read_in_parts() {
+        let path = std::env::temp_dir().join(format!("follow-{}.log", std::process::id()));
+        let contents: String = (0..MAX_POLL_LINES + 5)
+            .map(|i| format!("line {i}\n"))
+            .collect();
+        fs::write(&path, contents).unwrap();
+
+        let mut follower = Follower::new(&path);
+        let first = follower.poll();
+        assert_eq!(first.len(), MAX_POLL_LINES);
+        assert_eq!(first[0], "line 0");
+        let rest: Vec<String> = (MAX_POLL_LINES..MAX_POLL_LINES + 5)
+            .map(|i| format!("line {i}"))
+            .collect();
+        assert_eq!(follower.poll(), rest);
+        assert!(follower.poll().is_empty());
+        fs::remove_file(path).unwrap();
+    }
+}
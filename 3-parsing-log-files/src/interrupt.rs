@@ -0,0 +1,47 @@
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+// Number of the first SIGINT or SIGTERM received, 0 while running normally
+static SIGNAL: OnceLock<Arc<AtomicUsize>> = OnceLock::new();
+
+/// Installs handlers so SIGINT and SIGTERM ask the program to stop instead of
+/// killing it, letting it print what it has processed so far.
+///
+/// A second signal terminates the program immediately, in case stopping
+/// cleanly takes too long.
+pub fn install() -> io::Result<()> {
+    let signal = SIGNAL.get_or_init(|| Arc::new(AtomicUsize::new(0)));
+    let stop = Arc::new(AtomicBool::new(false));
+    for sig in [SIGINT, SIGTERM] {
+        // Registered first so it sees the flag before the second handler sets it
+        flag::register_conditional_shutdown(sig, 128 + sig, Arc::clone(&stop))?;
+        flag::register(sig, Arc::clone(&stop))?;
+        flag::register_usize(sig, Arc::clone(signal), sig as usize)?;
+    }
+    Ok(())
+}
+
+/// Returns whether a stop was requested with SIGINT or SIGTERM.
+pub fn interrupted() -> bool {
+    signal().is_some()
+}
+
+/// Returns the signal that interrupted the program, if any.
+pub fn signal() -> Option<i32> {
+    SIGNAL
+        .get()
+        .map(|signal| signal.load(Ordering::Relaxed) as i32)
+        .filter(|&signal| signal != 0)
+}
+
+/// Exits with the conventional status of a process killed by the signal that
+/// interrupted the program (130 for SIGINT), if one was received.
+pub fn exit_if_interrupted() {
+    if let Some(signal) = signal() {
+        eprintln!("Interrupted, results are partial");
+        std::process::exit(128 + signal);
+    }
+}
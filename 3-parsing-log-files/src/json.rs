@@ -0,0 +1,135 @@
+use crate::level::{Level, LevelClassifier};
+use crate::timestamp::{TimestampFormat, TimestampParser};
+use crate::zone::Zone;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+// Fields tried in order when no field name is configured
+const TIMESTAMP_FIELDS: [&str; 5] = ["timestamp", "@timestamp", "time", "ts", "datetime"];
+const LEVEL_FIELDS: [&str; 4] = ["level", "severity", "log.level", "lvl"];
+const MESSAGE_FIELDS: [&str; 3] = ["message", "msg", "@message"];
+
+/// Names of the fields holding the timestamp, level and message of JSON log
+/// entries, e.g. "timestamp" or a nested path such as "log.level".
+pub struct JsonFields {
+    timestamp: Vec<String>,
+    level: Vec<String>,
+    message: Vec<String>,
+    // Timestamps in JSON are usually RFC 3339 whatever the rest of the file uses
+    rfc3339: TimestampParser,
+}
+
+/// The fields of a JSON log entry.
+pub struct JsonEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: Option<Level>,
+    pub message: Option<String>,
+}
+
+impl JsonFields {
+    /// Creates a new `JsonFields`. Fields that are not given are looked up
+    /// under their usual names, such as "timestamp", "time" or "ts".
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - The path of the timestamp field.
+    /// * `level` - The path of the level field.
+    /// * `message` - The path of the message field.
+    /// * `zone` - The zone of timestamps written without an offset.
+    pub fn new(
+        timestamp: Option<&str>,
+        level: Option<&str>,
+        message: Option<&str>,
+        zone: Zone,
+    ) -> Self {
+        let fields = |field: Option<&str>, defaults: &[&str]| match field {
+            Some(field) => vec![field.to_string()],
+            None => defaults.iter().map(|f| f.to_string()).collect(),
+        };
+        JsonFields {
+            timestamp: fields(timestamp, &TIMESTAMP_FIELDS),
+            level: fields(level, &LEVEL_FIELDS),
+            message: fields(message, &MESSAGE_FIELDS),
+            rfc3339: TimestampParser::new(TimestampFormat::Rfc3339, zone)
+                .expect("the RFC 3339 pattern is valid"),
+        }
+    }
+
+    /// Parses a line holding a JSON object. Returns `None` if the line is not
+    /// JSON or has no timestamp, so it can be parsed as plain text instead.
+    ///
+    /// The level is read from the level field, or found in the message when
+    /// there is none. Numeric levels follow the bunyan and pino convention
+    /// (10 trace, 20 debug, 30 info, 40 warn, 50 error, 60 fatal).
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - A line from the log file.
+    /// * `parser` - Parses timestamp strings in the format of the file.
+    /// * `classifier` - Finds the level in level names and messages.
+    pub fn parse(
+        &self,
+        line: &str,
+        parser: &TimestampParser,
+        classifier: &LevelClassifier,
+    ) -> Option<JsonEntry> {
+        if !line.trim_start().starts_with('{') {
+            return None;
+        }
+        let value: Value = serde_json::from_str(line).ok()?;
+        let timestamp = match find(&value, &self.timestamp)? {
+            Value::String(text) => parser.parse(text).or_else(|| self.rfc3339.parse(text))?,
+            Value::Number(number) => epoch(number.as_f64()?)?,
+            _ => return None,
+        };
+        let message = find(&value, &self.message).map(|message| match message {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        });
+        let level = match find(&value, &self.level) {
+            Some(Value::String(name)) => classifier.classify(name),
+            Some(Value::Number(number)) => number.as_u64().and_then(numeric_level),
+            _ => message.as_deref().and_then(|m| classifier.classify(m)),
+        };
+        Some(JsonEntry {
+            timestamp,
+            level,
+            message,
+        })
+    }
+}
+
+// Returns the value of the first field found. A field name is looked up as
+// is first, then as a path of nested objects and arrays separated by dots.
+fn find<'a>(value: &'a Value, fields: &[String]) -> Option<&'a Value> {
+    fields.iter().find_map(|field| {
+        value.get(field).or_else(|| {
+            field.split('.').try_fold(value, |value, key| match value {
+                Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+                _ => value.get(key),
+            })
+        })
+    })
+}
+
+// Epoch timestamps are in seconds, or in milliseconds when too large to be
+// seconds, which would be past the year 5000.
+fn epoch(value: f64) -> Option<DateTime<Utc>> {
+    let millis = match value.abs() >= 1e11 {
+        true => value,
+        false => value * 1000.0,
+    };
+    DateTime::from_timestamp_millis(millis.round() as i64)
+}
+
+fn numeric_level(level: u64) -> Option<Level> {
+    match level {
+        0..=9 => None,
+        10..=19 => Some(Level::Trace),
+        20..=29 => Some(Level::Debug),
+        30..=39 => Some(Level::Info),
+        40..=49 => Some(Level::Warn),
+        50..=59 => Some(Level::Error),
+        _ => Some(Level::Fatal),
+    }
+}
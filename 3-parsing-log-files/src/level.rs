@@ -0,0 +1,153 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Severity of a log entry, from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    /// Every level, in increasing severity.
+    pub const ALL: [Level; 6] = [
+        Level::Trace,
+        Level::Debug,
+        Level::Info,
+        Level::Warn,
+        Level::Error,
+        Level::Fatal,
+    ];
+
+    /// Returns the upper case name of the level, e.g. "WARN".
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+            Level::Fatal => "FATAL",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Level::ALL
+            .into_iter()
+            .find(|level| level.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown level '{s}'"))
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Words recognised as levels out of the box, beyond the level names themselves
+const DEFAULT_ALIASES: [(&str, Level); 10] = [
+    ("information", Level::Info),
+    ("notice", Level::Info),
+    ("warning", Level::Warn),
+    ("err", Level::Error),
+    ("severe", Level::Error),
+    ("crit", Level::Fatal),
+    ("critical", Level::Fatal),
+    ("alert", Level::Fatal),
+    ("emerg", Level::Fatal),
+    ("panic", Level::Fatal),
+];
+
+/// Parses a `NAME=LEVEL` alias given on the command line, e.g. "sev3=warn".
+pub fn parse_alias(value: &str) -> Result<(String, Level), String> {
+    let (name, level) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=LEVEL, got '{value}'"))?;
+    Ok((name.trim().to_lowercase(), level.trim().parse()?))
+}
+
+/// Classifies log lines by the first level word they contain, ignoring case.
+pub struct LevelClassifier {
+    aliases: HashMap<String, Level>,
+    regex: Regex,
+}
+
+impl LevelClassifier {
+    /// Creates a new `LevelClassifier` recognising the level names, the
+    /// default aliases and the extra aliases.
+    ///
+    /// # Arguments
+    ///
+    /// * `extra_aliases` - Additional words and the level they stand for,
+    ///   overriding the defaults.
+    pub fn new(extra_aliases: &[(String, Level)]) -> Self {
+        let mut aliases: HashMap<String, Level> = Level::ALL
+            .into_iter()
+            .map(|level| (level.name().to_lowercase(), level))
+            .chain(DEFAULT_ALIASES.map(|(name, level)| (name.to_string(), level)))
+            .collect();
+        aliases.extend(extra_aliases.iter().cloned());
+
+        // Longest words first so "warning" is not matched as "warn"
+        let mut words: Vec<&String> = aliases.keys().collect();
+        words.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        let words: Vec<String> = words.into_iter().map(|w| regex::escape(w)).collect();
+        let regex = Regex::new(&format!(r"(?i)\b({})\b", words.join("|"))).unwrap();
+
+        LevelClassifier { aliases, regex }
+    }
+
+    /// Returns the level of the first level word in the line, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - A line from the log file.
+    pub fn classify(&self, line: &str) -> Option<Level> {
+        let word = self.regex.find(line)?.as_str().to_lowercase();
+        self.aliases.get(&word).copied()
+    }
+}
+
+/// Number of entries seen for each level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelCounts([u64; 6]);
+
+impl LevelCounts {
+    /// Counts one more entry at the given level.
+    pub fn add(&mut self, level: Level) {
+        self.0[level as usize] += 1;
+    }
+
+    /// Adds the counts of another set of counts to these.
+    pub fn merge(&mut self, other: &LevelCounts) {
+        for (count, other) in self.0.iter_mut().zip(other.0) {
+            *count += other;
+        }
+    }
+
+    /// Returns the number of entries at the given level.
+    pub fn get(&self, level: Level) -> u64 {
+        self.0[level as usize]
+    }
+}
+
+impl fmt::Display for LevelCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts: Vec<String> = Level::ALL
+            .into_iter()
+            .map(|level| format!("{level}: {}", self.get(level)))
+            .collect();
+        f.write_str(&counts.join(", "))
+    }
+}
@@ -1,119 +1,511 @@
-use flate2::read::GzDecoder;
+//! Counts log entries, errors and error rate per time bucket in log files.
+//!
+//! Each entry is classified into a level (TRACE, DEBUG, INFO, WARN, ERROR or FATAL) by the first
+//! level word it contains, ignoring case and accepting aliases such as "warning". Entries are
+//! grouped into time buckets of a fixed size (one hour by default), aligned on midnight in the
+//! --tz zone, and the error rate of a bucket is the share of its entries at ERROR or FATAL level.
+//! Entries of all files are merged in timestamp order, so logs from several hosts can be
+//! analysed together.
+//!
+//! Input
+//! - Files compressed with gzip, bzip2, xz or zstd are decompressed whatever their name, the
+//!   format being recognised from the first bytes. Every file in a zip archive is read as a
+//!   separate log file, and "-" reads standard input, which may be compressed too.
+//! - Timestamps are recognised in RFC 3339, classic syslog, Apache common log format and epoch
+//!   seconds or milliseconds, or in a strftime format given with --timestamp-format. Offsets
+//!   written with timestamps are applied, and timestamps without one are taken in the --tz zone.
+//! - Lines without a timestamp, such as stack traces, are continuation lines of the entry
+//!   before them. With --entry-start, entries start at the lines matching the pattern instead.
+//! - Lines holding a JSON object are parsed by field, the others as plain text.
+//! - With --since and --until, only the entries in that time range are counted. Plain files
+//!   written in time order are bisected to find the start of the range.
+//!
+//! Modes
+//! - By default the counts of every bucket are printed, empty buckets included, followed by the
+//!   totals for all files and for each file.
+//! - --clusters groups ERROR and FATAL messages into templates, masking variable tokens such as
+//!   numbers, IDs, paths and IP addresses, with the count, first and last time seen and an
+//!   example of each.
+//! - --anomalies prints only the buckets whose error count is more than --z-score deviations
+//!   above a --baseline of the --window buckets before them.
+//! - --follow reads the files as they grow, like `tail -F`, printing the buckets that change,
+//!   and prints the report once interrupted. Rotated and truncated files are reopened without
+//!   losing lines. Compressed files cannot be followed.
+//!
+//! Examples
+//! cargo run ../logs/dummy-log.txt
+//! cargo run ../logs/dummy-log.gz
+//! cargo run ../logs/logs.zip
+//! cat ../logs/*.gz | cargo run -- -
+//! cargo run -- "../logs/*.txt" ../logs/dummy-log.gz
+//! cargo run -- --bucket 15m --tz Europe/Paris ../logs/dummy-log.txt
+//! cargo run -- --format csv ../logs/dummy-log.txt > error-rate.csv
+//! cargo run -- --max-error-rate 5 --max-errors-per-bucket 100 /var/log/app.log || echo "deploy blocked"
+//! cargo run -- --timestamp-format syslog /var/log/syslog
+//! cargo run -- --entry-start "^\\[" /var/log/app.log
+//! cargo run -- --since "2h ago" /var/log/app.log
+//! cargo run -- --since "2024-03-01 09:00" --until "2024-03-01 12:00" --tz Europe/Paris /var/log/app.log
+//! cargo run -- --clusters ../logs/dummy-log.txt
+//! cargo run -- --anomalies --baseline median --window 48 --z-score 4 "/var/log/app.log*"
+//! cargo run -- --follow --bucket 1m /var/log/app.log
+//!
+//! Exit status codes
+//! 0 - Success
+//! 1 - No log file could be read
+//! 2 - Invalid command-line arguments
+//! 4 - A bucket has a higher error rate than --max-error-rate
+//! 5 - A bucket has more errors than --max-errors-per-bucket
+//! 128 + signal - Interrupted by SIGINT (130) or SIGTERM (143), results are partial
+
+mod anomaly;
+mod bucket;
+mod cluster;
+mod compression;
+mod follow;
+mod interrupt;
+mod json;
+mod level;
+mod merge;
+mod range;
+mod report;
+mod search;
+mod source;
+mod threshold;
+mod timestamp;
+mod zone;
+
+use anomaly::{Baseline, Detector};
+use bucket::{Bucket, BucketSize, Series};
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use cluster::Clusters;
+use follow::Follower;
+use json::JsonFields;
+use level::{Level, LevelClassifier};
+use merge::Merge;
+use range::{TimeBound, TimeRange};
 use regex::Regex;
-use std::env;
-use std::fs::File;
-
-/// Reads a log file, decompresses it if necessary, and calculates the error rate/
-/// Note, this function assumes that the log file contains timestamps in the format "YYYY-MM-DD HH:MM:SS-ZZ"
-/// and that the log entries contain the keyword "Error". It calculates the error rate for each hour in the log file.
-/// This function prints the error count for each hour and the total error count for the log file.
-/// The function uses the `flate2` crate to decompress gzipped files and the `regex` crate to match timestamps in the log file.
-/// The function reads the log file line by line, extracts the timestamp from each line, and calculates the error rate for each hour.
-/// The function should be adapted to match the specific format of the log file and the particular keyword.
+use report::OutputFormat;
+use source::{Entry, EntryParser, LogFile, LogReader, ParseOptions};
+use std::collections::BTreeSet;
+use std::thread;
+use std::time::Duration;
+use timestamp::TimestampFormat;
+use zone::Zone;
+
+// Command-line options for the program.
+#[derive(Parser)]
+#[command(
+    name = "log_error_rate",
+    about = "Count log entries, errors and error rate per time bucket in log files"
+)]
+struct Opts {
+    // Log files to analyse, optionally compressed or zipped, merged in timestamp order
+    #[clap(
+        required = true,
+        help = "Paths or glob patterns of the log files, e.g. \"/var/log/app-*.log.gz\", or - for standard input"
+    )]
+    log_files: Vec<String>,
+
+    // Timestamp format, detected from the first lines when omitted
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        help = "rfc3339, syslog, apache, epoch or a strftime format such as \"%d/%m/%Y %H:%M:%S\" (default: auto-detect)"
+    )]
+    timestamp_format: Option<TimestampFormat>,
+
+    // Extra words to recognise as levels, e.g. --level-alias sev1=fatal
+    #[clap(
+        long = "level-alias",
+        value_name = "NAME=LEVEL",
+        value_parser = level::parse_alias,
+        help = "Treat NAME as LEVEL (trace, debug, info, warn, error or fatal), can be repeated"
+    )]
+    level_aliases: Vec<(String, Level)>,
+
+    // Fields of JSON log entries, usual names such as "timestamp" or "msg" are tried when omitted
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Field holding the timestamp of JSON log entries, nested fields separated by dots (default: timestamp, @timestamp, time, ts or datetime)"
+    )]
+    json_timestamp_field: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Field holding the level of JSON log entries, e.g. log.level (default: level, severity, log.level or lvl)"
+    )]
+    json_level_field: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Field holding the message of JSON log entries, searched for a level word when there is no level field (default: message, msg or @message)"
+    )]
+    json_message_field: Option<String>,
+
+    // Time range of the entries analysed
+    #[clap(
+        long,
+        value_name = "TIME",
+        help = "Skip entries before TIME: \"2024-03-01T10:00:00Z\", \"2024-03-01 10:00\" in the --tz zone, or relative such as \"2h ago\""
+    )]
+    since: Option<TimeBound>,
+
+    #[clap(
+        long,
+        value_name = "TIME",
+        help = "Skip entries from TIME on, in the same formats as --since"
+    )]
+    until: Option<TimeBound>,
+
+    // Width of the time buckets
+    #[clap(long, value_enum, default_value = "1h", help = "Time bucket size")]
+    bucket: BucketSize,
+
+    // Zone buckets are reported in, also assumed for timestamps without an offset
+    #[clap(
+        long,
+        value_name = "ZONE",
+        allow_hyphen_values = true,
+        default_value = "UTC",
+        help = "Time zone to normalize timestamps to: UTC, an offset such as +02:00 or a name such as Europe/Paris"
+    )]
+    tz: Zone,
+
+    // Keep reading the files as they grow, like tail -F
+    #[clap(
+        long,
+        help = "Follow the log files as they grow, reopening them when rotated or truncated, until interrupted"
+    )]
+    follow: bool,
+
+    // How the results are printed
+    #[clap(
+        long,
+        value_enum,
+        default_value = "table",
+        help = "Output format: table for reading, json or csv for charts and spreadsheets"
+    )]
+    format: OutputFormat,
+
+    // Thresholds to gate deploys on, buckets above them fail the run
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        value_parser = threshold::parse_rate,
+        help = "Exit with status 4 if a bucket has a higher error rate, in percent"
+    )]
+    max_error_rate: Option<f64>,
+
+    #[clap(
+        long,
+        value_name = "COUNT",
+        help = "Exit with status 5 if a bucket has more errors"
+    )]
+    max_errors_per_bucket: Option<u64>,
+
+    // Pattern of the first line of multiline entries such as stack traces
+    #[clap(
+        long,
+        value_name = "REGEX",
+        help = "Regex matching the first line of each entry, other lines being appended to the entry before them (default: lines with a timestamp)"
+    )]
+    entry_start: Option<Regex>,
+
+    // Group error messages into templates instead of counting them per bucket
+    #[clap(
+        long,
+        help = "Report ERROR and FATAL messages grouped into templates, with variable parts such as numbers, IDs, paths and IPs masked"
+    )]
+    clusters: bool,
+
+    #[clap(
+        long,
+        value_name = "RATIO",
+        default_value = "0.4",
+        value_parser = parse_similarity,
+        help = "Share of equal tokens, between 0 and 1, for a message to join a template"
+    )]
+    cluster_similarity: f64,
+
+    // Spot the unusual buckets rather than reading through all of them
+    #[clap(
+        long,
+        conflicts_with = "clusters",
+        help = "Report only the buckets whose error count is unusually high compared with the buckets before them"
+    )]
+    anomalies: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value = "mean",
+        help = "Baseline of the expected error count: mean and standard deviation, ewma (exponentially weighted) or median and median absolute deviation"
+    )]
+    baseline: Baseline,
+
+    #[clap(
+        long,
+        value_name = "BUCKETS",
+        default_value = "24",
+        value_parser = anomaly::parse_window,
+        help = "Number of previous buckets the baseline is computed from"
+    )]
+    window: usize,
+
+    #[clap(
+        long,
+        value_name = "SCORE",
+        default_value = "3",
+        value_parser = anomaly::parse_z_score,
+        help = "Report buckets with more errors than this many deviations above the baseline"
+    )]
+    z_score: f64,
+}
+
+// Parses the similarity ratio of --cluster-similarity.
+fn parse_similarity(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("expected a number between 0 and 1, got '{value}'")),
+    }
+}
+
+// Time to wait for new lines in follow mode
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Counts gathered from the entries of every log file.
+struct Analysis {
+    // Counts per bucket for the error rate calculation, and per file
+    series: Series,
+    per_source: Vec<Bucket>,
+    // Templates of the error messages, when asked for
+    clusters: Option<Clusters>,
+}
+
+impl Analysis {
+    fn new(files: &[LogFile], opts: &Opts) -> Self {
+        Analysis {
+            series: Series::new(opts.bucket, opts.tz),
+            per_source: vec![Bucket::default(); files.len()],
+            clusters: opts
+                .clusters
+                .then(|| Clusters::new(opts.cluster_similarity)),
+        }
+    }
+
+    // Counts an entry and returns the start of its bucket.
+    fn add(&mut self, entry: &Entry) -> DateTime<Utc> {
+        // Count the log entry in its bucket, under its level
+        let start = self.series.add(entry.timestamp, entry.level);
+        self.per_source[entry.source].add(entry.level);
+        if let Some(clusters) = self.clusters.as_mut() {
+            if matches!(entry.level, Some(Level::Error | Level::Fatal)) {
+                clusters.add(&entry.message, entry.timestamp);
+            }
+        }
+        start
+    }
+
+    // Prints the results, then lists the buckets above the thresholds and returns the exit status.
+    fn finish(&self, files: &[LogFile], opts: &Opts) -> i32 {
+        match &self.clusters {
+            Some(clusters) => report::print_clusters(opts.format, clusters, opts.tz),
+            None if opts.anomalies => {
+                let detector = Detector {
+                    baseline: opts.baseline,
+                    window: opts.window,
+                    z_score: opts.z_score,
+                };
+                report::print_anomalies(opts.format, &self.series, &detector, opts.tz)
+            }
+            None => report::print(opts.format, &self.series, opts.tz, files, &self.per_source),
+        }
+        threshold::check(
+            &self.series,
+            opts.max_error_rate,
+            opts.max_errors_per_bucket,
+        )
+    }
+}
+
+/// Reads every log file to the end, merging their entries in timestamp order, and prints the
+/// report of the chosen mode. On SIGINT or SIGTERM it stops reading and still prints the counts
+/// of the lines processed so far, as a complete JSON or CSV document if asked to.
 ///
 /// # Arguments
 ///
-/// * `file_path` - A string slice that holds the path to the log file
+/// * `files` - The log files
+/// * `opts` - The command-line options: bucket size, time zone, output format and thresholds
+/// * `parsing` - How lines are parsed: timestamp format, level words and JSON fields
+///
+/// # Returns
 ///
-/// # Example
-/// To locate the log files, compress/uncompress them, and read their contents, run the following commands as needed:
-/// find ../logs -name "*.txt"
-/// find ../logs -name "*.gz"
-/// gzip ../logs/dummy-log.txt
-/// unzip ../logs/logs.zip //these are zipped log files from GitHub codespaces
-/// cat ../logs/dummy-log.txt
-/// cargo run ../logs/dummy-log.txt
-/// cargo run ../logs/dummy-log.gz
-
-fn read_buffer(file_path: &str) {
-    // Initialise variables for error rate calculation
-    let mut total_entries = 0;
-    let mut error_entries = 0;
-    let mut current_hour = None;
-
-    // Regular expression to match timestamps in the log file
-    // The regex pattern `(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}-\d{2})` matches "YYYY-MM-DD HH:MM:SS-ZZ":
-    // - `\d{4}`: exactly 4 digits (year)
-    // - `-`: a hyphen
-    // - `\d{2}`: exactly 2 digits (month)
-    // - `-`: a hyphen
-    // - `\d{2}`: exactly 2 digits (day)
-    // - ` `: a space
-    // - `\d{2}`: exactly 2 digits (hour)
-    // - `:`: a colon
-    // - `\d{2}`: exactly 2 digits (minute)
-    // - `:`: a colon
-    // - `\d{2}`: exactly 2 digits (second)
-    // - `-`: a hyphen
-    // - `\d{2}`: exactly 2 digits (timezone offset)
-    let timestamp_regex = Regex::new(r"(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}-\d{2})").unwrap();
-    let error_keyword = "Error"; // Keyword to search for in log entries
-
-    // Open the log file.
-    let file = File::open(file_path).unwrap();
-    use std::io::{BufRead, BufReader};
-
-    // Create a buffered reader, decompressing if the file is gzipped
-    let reader: Box<dyn BufRead> = match file_path.ends_with(".gz") {
-        true => {
-            // Decompress gzipped file.
-            let decompressor = GzDecoder::new(file);
-            Box::new(BufReader::new(decompressor))
+/// The exit status: 1 if no file could be read, 4 or 5 if a bucket exceeds the error thresholds, 0 otherwise.
+fn read_buffer(files: &[LogFile], opts: &Opts, parsing: &ParseOptions) -> i32 {
+    let mut analysis = Analysis::new(files, opts);
+
+    // Open every log file, skipping the ones that cannot be read
+    let mut readers = Vec::new();
+    for (source, file) in files.iter().enumerate() {
+        match LogReader::open(file, source, parsing) {
+            Ok(reader) => readers.push(reader),
+            Err(e) => eprintln!("Error opening {file}: {e}"),
         }
-        false => Box::new(BufReader::new(file)),
+    }
+
+    let status = match readers.is_empty() {
+        true => 1, // No log file could be read
+        false => 0,
     };
 
-    // Read the log file line by line
-    for line in reader.lines() {
-        let line = match line {
-            Ok(line) => line,
-            Err(error) => {
-                eprintln!("Error reading line: {}", error);
+    // Read the entries of all files in timestamp order
+    for entry in Merge::new(readers) {
+        if interrupt::interrupted() {
+            break;
+        }
+        analysis.add(&entry);
+    }
+
+    match (status, analysis.finish(files, opts)) {
+        (0, status) => status,
+        (status, _) => status,
+    }
+}
+
+/// Follows log files as they grow and prints the counts of every bucket that receives entries
+/// as they arrive, then the report of the chosen mode once interrupted with SIGINT or SIGTERM.
+///
+/// Entries are counted in the order they are read rather than merged, since a file that is not
+/// written to would hold back the others. An entry is counted once the next one starts, its
+/// file stays idle for a poll interval or following stops, so continuation lines written later
+/// are not part of it.
+///
+/// # Arguments
+///
+/// * `files` - The log files
+/// * `opts` - The command-line options
+/// * `parsing` - How lines are parsed
+fn follow(files: &[LogFile], opts: &Opts, parsing: &ParseOptions) -> i32 {
+    let mut analysis = Analysis::new(files, opts);
+    let mut followers: Vec<(usize, Follower)> = Vec::new();
+    for (source, file) in files.iter().enumerate() {
+        if file.is_stdin() || file.member.is_some() {
+            eprintln!("Cannot follow {file}");
+        } else if compression::sniff_file(&file.path).is_some() {
+            eprintln!("Cannot follow compressed file {file}");
+        } else {
+            followers.push((source, Follower::new(&file.path)));
+        }
+    }
+    if followers.is_empty() {
+        return 1; // No log file could be read
+    }
+    // Bucket counts are printed as they change when reading them
+    let live = opts.format == OutputFormat::Table && !opts.clusters;
+    // Created from the first lines of each file, once it has some
+    let mut parsers: Vec<Option<EntryParser>> = files.iter().map(|_| None).collect();
+
+    while !interrupt::interrupted() {
+        let mut updated = BTreeSet::new();
+        let mut idle = true;
+        for (source, follower) in followers.iter_mut() {
+            let lines = follower.poll();
+            idle &= lines.is_empty();
+            if lines.is_empty() {
+                // Count the last entry once the file is idle, rather than wait for the next one
+                if let Some(entry) = parsers[*source].as_mut().and_then(EntryParser::flush) {
+                    updated.insert(analysis.add(&entry));
+                }
                 continue;
             }
-        };
-
-        // Extract timestamp from the log line
-        if let Some(captures) = timestamp_regex.captures(&line) {
-            let timestamp = &captures[1];
-            // Extract the date and hour part of the timestamp, assuming it's in "YYYY-MM-DD HH:MM:SS-ZZ" format
-            let date = &timestamp[0..10];
-            let hour = &timestamp[11..13];
-            let date_hour = format!("{}, Hour: {}", date, hour);
-
-            // Update the current hour and reset counters if necessary
-            if current_hour != Some(date_hour.to_string()) {
-                // Calculate and print error count for the previous hour
-                if let Some(prev_hour) = current_hour.take() {
-                    println!("{prev_hour} - Error Count: {error_entries}");
+            if parsers[*source].is_none() {
+                let sample = &lines[..lines.len().min(source::DETECT_SAMPLE_LINES)];
+                let file = &files[*source];
+                match EntryParser::new(file, *source, sample, parsing) {
+                    Ok(parser) => parsers[*source] = Some(parser),
+                    Err(e) => {
+                        eprintln!("Error reading {file}: {e}");
+                        continue;
+                    }
+                }
+            }
+            let Some(parser) = parsers[*source].as_mut() else {
+                continue;
+            };
+            for line in lines {
+                if let Some(entry) = parser.push(line) {
+                    updated.insert(analysis.add(&entry));
                 }
-
-                // Reset counters for the new hour
-                error_entries = 0;
-                current_hour = Some(date_hour.to_string());
             }
+        }
 
-            // Check if the log entry contains an error
-            if line.contains(error_keyword) {
-                error_entries += 1;
-                total_entries += 1;
+        // Print the buckets that changed with their counts so far
+        if live {
+            for start in &updated {
+                report::print_bucket(&analysis.series, *start);
             }
         }
+        // Keep reading without waiting while files have lines left
+        if idle {
+            thread::sleep(POLL_INTERVAL);
+        }
     }
 
-    println!("Total error count for current log: {total_entries}");
-}
-fn main() {
-    // Collect command-line arguments
-    let args: Vec<String> = env::args().collect();
+    // Count the last entry of every file, still waiting for continuation lines
+    for entry in parsers.iter_mut().flatten().filter_map(EntryParser::flush) {
+        let start = analysis.add(&entry);
+        if live {
+            report::print_bucket(&analysis.series, start);
+        }
+    }
 
-    // Check if the user provided a path argument
-    if args.len() < 2 {
-        eprintln!("Usage: log_error_rate <log_file_path>");
-        std::process::exit(1);
+    if live {
+        println!();
     }
+    analysis.finish(files, opts)
+}
 
-    let log_file_path = &args[1];
+fn main() {
+    // Parse command-line arguments
+    let opts = Opts::parse();
 
-    read_buffer(log_file_path);
+    if let Err(e) = interrupt::install() {
+        eprintln!("Failed to install signal handlers: {}", e);
+    }
+    // Relative times such as "2h ago" are counted back from the start of the run
+    let now = Utc::now();
+    let parsing = ParseOptions {
+        format: opts.timestamp_format.clone(),
+        zone: opts.tz,
+        classifier: LevelClassifier::new(&opts.level_aliases),
+        json: JsonFields::new(
+            opts.json_timestamp_field.as_deref(),
+            opts.json_level_field.as_deref(),
+            opts.json_message_field.as_deref(),
+            opts.tz,
+        ),
+        entry_start: opts.entry_start.clone(),
+        range: TimeRange {
+            since: opts.since.map(|since| since.resolve(opts.tz, now)),
+            until: opts.until.map(|until| until.resolve(opts.tz, now)),
+        },
+    };
+    let files = source::expand(&opts.log_files);
+    // Exit status
+    let status = if opts.follow {
+        // Following only ends when interrupted, that is not an error
+        follow(&files, &opts, &parsing)
+    } else {
+        let status = read_buffer(&files, &opts, &parsing);
+        interrupt::exit_if_interrupted();
+        status
+    };
+    // Check if any threshold was exceeded
+    if status != 0 {
+        std::process::exit(status);
+    }
 }
@@ -0,0 +1,75 @@
+use crate::source::Entry;
+use chrono::{DateTime, Utc};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+// The next entry of one input, ordered by timestamp then by input so the
+// merge is stable when several files log the same instant.
+struct Head {
+    entry: Entry,
+    input: usize,
+}
+
+impl Head {
+    fn key(&self) -> (DateTime<Utc>, usize) {
+        (self.entry.timestamp, self.input)
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Merges several streams of entries, each in timestamp order, into a single
+/// stream in timestamp order. Only the next entry of each stream is held in
+/// memory, so files of any size can be merged.
+pub struct Merge<I> {
+    inputs: Vec<I>,
+    heap: BinaryHeap<Reverse<Head>>,
+}
+
+impl<I: Iterator<Item = Entry>> Merge<I> {
+    /// Creates a new `Merge` over the given streams.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - The streams to merge, usually one per log file.
+    pub fn new(mut inputs: Vec<I>) -> Self {
+        let mut heap = BinaryHeap::with_capacity(inputs.len());
+        for (input, stream) in inputs.iter_mut().enumerate() {
+            if let Some(entry) = stream.next() {
+                heap.push(Reverse(Head { entry, input }));
+            }
+        }
+        Merge { inputs, heap }
+    }
+}
+
+impl<I: Iterator<Item = Entry>> Iterator for Merge<I> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        let Reverse(Head { entry, input }) = self.heap.pop()?;
+        // Refill from the stream the entry came from
+        if let Some(next) = self.inputs[input].next() {
+            self.heap.push(Reverse(Head { entry: next, input }));
+        }
+        Some(entry)
+    }
+}
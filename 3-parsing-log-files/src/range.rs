@@ -0,0 +1,108 @@
+use crate::zone::Zone;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use regex::Regex;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+// "2h ago", "30 minutes ago"
+static RELATIVE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+)\s*([a-z]+)\s+ago$").unwrap());
+
+// Wall clock formats accepted for times without an offset
+const LOCAL_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// A bound of the time range given on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeBound {
+    /// An instant written with an offset, e.g. "2024-03-01T10:00:00Z".
+    Instant(DateTime<Utc>),
+    /// A wall clock time in the zone of the analysis, e.g. "2024-03-01 10:00".
+    Local(NaiveDateTime),
+    /// A time before the start of the run, e.g. "2h ago".
+    Ago(TimeDelta),
+}
+
+impl TimeBound {
+    /// Returns the instant the bound stands for.
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - The zone of wall clock times.
+    /// * `now` - The time relative bounds are counted back from.
+    pub fn resolve(self, zone: Zone, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            TimeBound::Instant(instant) => instant,
+            TimeBound::Local(local) => zone.instant(local).unwrap_or_else(|| local.and_utc()),
+            TimeBound::Ago(delta) => now - delta,
+        }
+    }
+}
+
+impl FromStr for TimeBound {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("now") {
+            return Ok(TimeBound::Ago(TimeDelta::zero()));
+        }
+        if let Some(captures) = RELATIVE.captures(&text.to_lowercase()) {
+            let count: i64 = captures[1]
+                .parse()
+                .map_err(|_| format!("invalid count in '{text}'"))?;
+            let unit = match &captures[2] {
+                "s" | "sec" | "secs" | "second" | "seconds" => TimeDelta::seconds(1),
+                "m" | "min" | "mins" | "minute" | "minutes" => TimeDelta::minutes(1),
+                "h" | "hr" | "hrs" | "hour" | "hours" => TimeDelta::hours(1),
+                "d" | "day" | "days" => TimeDelta::days(1),
+                "w" | "week" | "weeks" => TimeDelta::weeks(1),
+                unit => return Err(format!("unknown time unit '{unit}' in '{text}'")),
+            };
+            return i32::try_from(count)
+                .ok()
+                .and_then(|count| unit.checked_mul(count))
+                .map(TimeBound::Ago)
+                .ok_or_else(|| format!("'{text}' is too far back"));
+        }
+        if let Ok(instant) = DateTime::parse_from_rfc3339(&text.replacen(' ', "T", 1)) {
+            return Ok(TimeBound::Instant(instant.to_utc()));
+        }
+        if let Some(local) = LOCAL_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        {
+            return Ok(TimeBound::Local(local));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            return Ok(TimeBound::Local(date.and_time(Default::default())));
+        }
+        Err(format!(
+            "expected a time such as \"2024-03-01T10:00:00Z\", \"2024-03-01 10:00\" or \"2h ago\", got '{text}'"
+        ))
+    }
+}
+
+/// The time range entries are analysed in, from `since` included to `until`
+/// excluded. Missing bounds leave the range open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl TimeRange {
+    /// Returns whether an instant is within the range.
+    ///
+    /// # Arguments
+    ///
+    /// * `instant` - The time of an entry.
+    pub fn contains(&self, instant: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| instant >= since)
+            && self.until.is_none_or(|until| instant < until)
+    }
+}
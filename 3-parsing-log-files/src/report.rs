@@ -0,0 +1,402 @@
+use crate::anomaly::Detector;
+use crate::bucket::{Bucket, Series};
+use crate::cluster::Clusters;
+use crate::level::{Level, LevelCounts};
+use crate::source::LogFile;
+use crate::zone::Zone;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// How the results are printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One line per bucket followed by the totals, for reading.
+    Table,
+    /// A single JSON document with the buckets, totals and files.
+    Json,
+    /// One row per bucket with a header row, for spreadsheets.
+    Csv,
+}
+
+// Counts of a bucket, the totals or a file in the JSON output
+#[derive(Serialize)]
+struct Counts {
+    entries: u64,
+    errors: u64,
+    error_rate: f64,
+    levels: Levels,
+}
+
+impl From<&Bucket> for Counts {
+    fn from(bucket: &Bucket) -> Self {
+        Counts {
+            entries: bucket.total,
+            errors: bucket.errors(),
+            error_rate: bucket.error_rate(),
+            levels: Levels::from(&bucket.levels),
+        }
+    }
+}
+
+// Per level counts, in increasing severity
+#[derive(Serialize)]
+struct Levels {
+    trace: u64,
+    debug: u64,
+    info: u64,
+    warn: u64,
+    error: u64,
+    fatal: u64,
+}
+
+impl From<&LevelCounts> for Levels {
+    fn from(levels: &LevelCounts) -> Self {
+        Levels {
+            trace: levels.get(Level::Trace),
+            debug: levels.get(Level::Debug),
+            info: levels.get(Level::Info),
+            warn: levels.get(Level::Warn),
+            error: levels.get(Level::Error),
+            fatal: levels.get(Level::Fatal),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BucketRecord {
+    start: String,
+    end: String,
+    #[serde(flatten)]
+    counts: Counts,
+}
+
+#[derive(Serialize)]
+struct FileRecord {
+    file: String,
+    #[serde(flatten)]
+    counts: Counts,
+}
+
+#[derive(Serialize)]
+struct ClusterRecord {
+    template: String,
+    count: u64,
+    first_seen: String,
+    last_seen: String,
+    example: String,
+}
+
+#[derive(Serialize)]
+struct JsonClusters {
+    time_zone: String,
+    clusters: Vec<ClusterRecord>,
+}
+
+#[derive(Serialize)]
+struct AnomalyRecord {
+    start: String,
+    end: String,
+    entries: u64,
+    errors: u64,
+    expected_errors: f64,
+    z_score: f64,
+}
+
+#[derive(Serialize)]
+struct JsonAnomalies {
+    bucket_size: String,
+    time_zone: String,
+    baseline: String,
+    window: usize,
+    z_score: f64,
+    anomalies: Vec<AnomalyRecord>,
+}
+
+#[derive(Serialize)]
+struct JsonReport {
+    bucket_size: String,
+    time_zone: String,
+    buckets: Vec<BucketRecord>,
+    total: Counts,
+    files: Vec<FileRecord>,
+}
+
+/// Prints every bucket, including empty ones and the last one, then the totals.
+///
+/// # Arguments
+///
+/// * `format` - How to print the results.
+/// * `series` - The counts of every bucket.
+/// * `zone` - The time zone bucket start times are in.
+/// * `files` - The log files read.
+/// * `per_source` - The counts of each file, in the same order.
+pub fn print(
+    format: OutputFormat,
+    series: &Series,
+    zone: Zone,
+    files: &[LogFile],
+    per_source: &[Bucket],
+) {
+    match format {
+        OutputFormat::Table => print_table(series, files, per_source),
+        OutputFormat::Json => print_json(series, zone, files, per_source),
+        OutputFormat::Csv => print_csv(series),
+    }
+}
+
+/// Prints the line of one bucket in the table format.
+///
+/// # Arguments
+///
+/// * `series` - The counts of every bucket.
+/// * `start` - The start of the bucket.
+pub fn print_bucket(series: &Series, start: DateTime<Utc>) {
+    let local = series.zone().local(start);
+    println!("{} - {}", local.format("%Y-%m-%d %H:%M"), series.get(start));
+}
+
+fn print_table(series: &Series, files: &[LogFile], per_source: &[Bucket]) {
+    for (start, _) in series.iter() {
+        print_bucket(series, start);
+    }
+    let total = series.total();
+    println!(
+        "Total for current log: {} entries, {} errors ({:.2}%) over {} buckets of {}",
+        total.total,
+        total.errors(),
+        total.error_rate(),
+        series.len(),
+        series.size()
+    );
+    if files.len() > 1 {
+        for (file, bucket) in files.iter().zip(per_source) {
+            println!(
+                "  {}: {} entries, {} errors ({:.2}%)",
+                file,
+                bucket.total,
+                bucket.errors(),
+                bucket.error_rate()
+            );
+        }
+    }
+}
+
+fn print_json(series: &Series, zone: Zone, files: &[LogFile], per_source: &[Bucket]) {
+    let report = JsonReport {
+        bucket_size: series.size().to_string(),
+        time_zone: zone.to_string(),
+        buckets: series
+            .iter()
+            .map(|(start, bucket)| {
+                let (start, end) = bounds(series, start);
+                BucketRecord {
+                    start,
+                    end,
+                    counts: Counts::from(&bucket),
+                }
+            })
+            .collect(),
+        total: Counts::from(&series.total()),
+        files: files
+            .iter()
+            .zip(per_source)
+            .map(|(file, bucket)| FileRecord {
+                file: file.to_string(),
+                counts: Counts::from(bucket),
+            })
+            .collect(),
+    };
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => eprintln!("Error writing JSON: {}", e),
+    }
+}
+
+fn print_csv(series: &Series) {
+    let levels: Vec<String> = Level::ALL
+        .into_iter()
+        .map(|level| level.name().to_lowercase())
+        .collect();
+    println!("start,end,entries,errors,error_rate,{}", levels.join(","));
+    for (start, bucket) in series.iter() {
+        let (start, end) = bounds(series, start);
+        let counts: Vec<String> = Level::ALL
+            .into_iter()
+            .map(|level| bucket.levels.get(level).to_string())
+            .collect();
+        println!(
+            "{start},{end},{},{},{:.2},{}",
+            bucket.total,
+            bucket.errors(),
+            bucket.error_rate(),
+            counts.join(",")
+        );
+    }
+}
+
+/// Prints the templates of the error messages, most frequent first, with
+/// their count, the first and last time they were seen and an example.
+///
+/// # Arguments
+///
+/// * `format` - How to print the results.
+/// * `clusters` - The templates of the error messages.
+/// * `zone` - The time zone times are printed in.
+pub fn print_clusters(format: OutputFormat, clusters: &Clusters, zone: Zone) {
+    let clusters = clusters.sorted();
+    match format {
+        OutputFormat::Table => {
+            println!(
+                "{:>7}  {:<16}  {:<16}  Template",
+                "Count", "First seen", "Last seen"
+            );
+            for cluster in &clusters {
+                let seen = |instant| zone.local(instant).format("%Y-%m-%d %H:%M");
+                println!(
+                    "{:>7}  {:<16}  {:<16}  {}",
+                    cluster.count,
+                    seen(cluster.first_seen),
+                    seen(cluster.last_seen),
+                    cluster.template()
+                );
+                // Continuation lines are lined up under the first one
+                println!(
+                    "{:>7}  e.g. {}",
+                    "",
+                    cluster.example.replace('\n', &format!("\n{:>14}", ""))
+                );
+            }
+            let total: u64 = clusters.iter().map(|cluster| cluster.count).sum();
+            println!(
+                "Total for current log: {} error messages in {} templates",
+                total,
+                clusters.len()
+            );
+        }
+        OutputFormat::Json => {
+            let report = JsonClusters {
+                time_zone: zone.to_string(),
+                clusters: clusters
+                    .iter()
+                    .map(|cluster| ClusterRecord {
+                        template: cluster.template(),
+                        count: cluster.count,
+                        first_seen: zone.format_instant(cluster.first_seen),
+                        last_seen: zone.format_instant(cluster.last_seen),
+                        example: cluster.example.clone(),
+                    })
+                    .collect(),
+            };
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{json}"),
+                Err(e) => eprintln!("Error writing JSON: {}", e),
+            }
+        }
+        OutputFormat::Csv => {
+            println!("count,first_seen,last_seen,template,example");
+            for cluster in &clusters {
+                println!(
+                    "{},{},{},{},{}",
+                    cluster.count,
+                    zone.format_instant(cluster.first_seen),
+                    zone.format_instant(cluster.last_seen),
+                    csv_field(&cluster.template()),
+                    csv_field(&cluster.example)
+                );
+            }
+        }
+    }
+}
+
+/// Prints the buckets with an unusual number of errors, in time order, with
+/// their error count, the count expected from the baseline and their score.
+///
+/// # Arguments
+///
+/// * `format` - How to print the results.
+/// * `series` - The counts of every bucket.
+/// * `detector` - How anomalies are detected.
+/// * `zone` - The time zone bucket start times are in.
+pub fn print_anomalies(format: OutputFormat, series: &Series, detector: &Detector, zone: Zone) {
+    let anomalies = detector.detect(series);
+    match format {
+        OutputFormat::Table => {
+            for anomaly in &anomalies {
+                println!(
+                    "{} - Errors: {} in {} entries, expected {:.1} (z-score {:.2})",
+                    zone.local(anomaly.start).format("%Y-%m-%d %H:%M"),
+                    anomaly.bucket.errors(),
+                    anomaly.bucket.total,
+                    anomaly.expected,
+                    anomaly.score
+                );
+            }
+            println!(
+                "Total for current log: {} anomalous buckets out of {}, more than {} deviations above the {} of the previous {} buckets",
+                anomalies.len(),
+                series.len(),
+                detector.z_score,
+                detector.baseline,
+                detector.window
+            );
+        }
+        OutputFormat::Json => {
+            let report = JsonAnomalies {
+                bucket_size: series.size().to_string(),
+                time_zone: zone.to_string(),
+                baseline: detector.baseline.to_string(),
+                window: detector.window,
+                z_score: detector.z_score,
+                anomalies: anomalies
+                    .iter()
+                    .map(|anomaly| {
+                        let (start, end) = bounds(series, anomaly.start);
+                        AnomalyRecord {
+                            start,
+                            end,
+                            entries: anomaly.bucket.total,
+                            errors: anomaly.bucket.errors(),
+                            expected_errors: anomaly.expected,
+                            z_score: anomaly.score,
+                        }
+                    })
+                    .collect(),
+            };
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{json}"),
+                Err(e) => eprintln!("Error writing JSON: {}", e),
+            }
+        }
+        OutputFormat::Csv => {
+            println!("start,end,entries,errors,expected_errors,z_score");
+            for anomaly in &anomalies {
+                let (start, end) = bounds(series, anomaly.start);
+                println!(
+                    "{start},{end},{},{},{:.2},{:.2}",
+                    anomaly.bucket.total,
+                    anomaly.bucket.errors(),
+                    anomaly.expected,
+                    anomaly.score
+                );
+            }
+        }
+    }
+}
+
+// Quotes a CSV field when it holds a comma, a quote or a line break.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+// Returns the start and end of a bucket as RFC 3339 timestamps with the
+// offset of the zone at that time.
+fn bounds(series: &Series, start: DateTime<Utc>) -> (String, String) {
+    let zone = series.zone();
+    (
+        zone.format_instant(start),
+        zone.format_instant(series.end(start)),
+    )
+}
@@ -0,0 +1,220 @@
+use crate::source::EntryParser;
+use chrono::{DateTime, Utc};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+// Below this many bytes the rest is read line by line rather than bisected
+const MIN_SPAN: u64 = 64 * 1024;
+
+// Lines read at each probe to find one with a timestamp
+const PROBE_LINES: usize = 100;
+
+/// Finds where to start reading a plain text log file written in time order
+/// to get the entries from `since` on, by binary search on byte offsets.
+///
+/// Returns the start of a line whose entry is older than `since`, so no later
+/// entry is skipped, or 0 when the file must be read from the start. Timestamps
+/// found out of order while searching also give 0, since the file cannot then
+/// be searched.
+///
+/// # Arguments
+///
+/// * `path` - The path of the log file, which must not be compressed.
+/// * `parser` - Parses the timestamps of the file.
+/// * `since` - The time of the first entry wanted.
+pub fn start_offset(path: &Path, parser: &EntryParser, since: DateTime<Utc>) -> io::Result<u64> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let length = reader.get_ref().metadata()?.len();
+
+    // The start is between `low` and `high`, the timestamps found at those
+    // offsets being older than `since` and at or after it respectively
+    let (mut low, mut high) = (0, length);
+    let (mut start, mut before, mut after) = (0, None, None);
+    while high - low > MIN_SPAN {
+        let middle = low + (high - low) / 2;
+        match probe(&mut reader, middle, parser)? {
+            Some((offset, timestamp)) if timestamp < since => {
+                if before.is_some_and(|before| timestamp < before) {
+                    return Ok(0);
+                }
+                (low, start, before) = (middle, offset, Some(timestamp));
+            }
+            Some((_, timestamp)) => {
+                if after.is_some_and(|after| timestamp > after) {
+                    return Ok(0);
+                }
+                (high, after) = (middle, Some(timestamp));
+            }
+            // No timestamp near the middle, look before it to be safe
+            None => high = middle,
+        }
+    }
+    Ok(start)
+}
+
+// Returns the offset and timestamp of the first line with a timestamp starting
+// after an offset, skipping the rest of the line the offset falls in.
+fn probe(
+    reader: &mut BufReader<File>,
+    at: u64,
+    parser: &EntryParser,
+) -> io::Result<Option<(u64, DateTime<Utc>)>> {
+    reader.seek(SeekFrom::Start(at))?;
+    let mut offset = at;
+    let mut line = Vec::new();
+    if at > 0 {
+        offset += reader.read_until(b'\n', &mut line)? as u64;
+    }
+    for _ in 0..PROBE_LINES {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        if let Some(timestamp) = parser.timestamp(&String::from_utf8_lossy(&line)) {
+            return Ok(Some((offset, timestamp)));
+        }
+        offset += read as u64;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::JsonFields;
+    use crate::level::LevelClassifier;
+    use crate::range::TimeRange;
+    use crate::source::{LogFile, ParseOptions};
+    use crate::timestamp::TimestampFormat;
+    use crate::zone::Zone;
+    use chrono::TimeDelta;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Lines written by the tests, enough for the file to be bisected
+    const LINES: i64 = 20_000;
+
+    fn options() -> ParseOptions {
+        ParseOptions {
+            format: Some(TimestampFormat::Rfc3339),
+            zone: Zone::default(),
+            classifier: LevelClassifier::new(&[]),
+            json: JsonFields::new(None, None, None, Zone::default()),
+            entry_start: None,
+            range: TimeRange::default(),
+        }
+    }
+
+    fn epoch() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn line(second: i64) -> String {
+        let timestamp = epoch() + TimeDelta::seconds(second);
+        format!("{} INFO request {second} handled\n", timestamp.to_rfc3339())
+    }
+
+    // Writes a log file with one entry per second in the given order, with
+    // a few continuation lines after every `trace`th entry
+    fn write_log(name: &str, seconds: &[i64], trace: i64) -> PathBuf {
+        let mut contents = String::new();
+        for &second in seconds {
+            contents.push_str(&line(second));
+            if second % trace == 0 {
+                contents.push_str("java.lang.IllegalStateException: boom\n");
+                contents.push_str("    at com.example.Worker.run(Worker.java:42)\n");
+            }
+        }
+        let path = std::env::temp_dir().join(format!("search-{}-{name}.log", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn start(path: &Path, second: i64) -> u64 {
+        let options = options();
+        let file = LogFile {
+            path: path.to_path_buf(),
+            member: None,
+        };
+        let parser = EntryParser::new(&file, 0, &[], &options).unwrap();
+        start_offset(path, &parser, epoch() + TimeDelta::seconds(second)).unwrap()
+    }
+
+    // No entry from `second` on may be skipped, and the offset must start an entry
+    fn assert_safe(path: &Path, offset: u64, second: i64) {
+        let contents = fs::read_to_string(path).unwrap();
+        let (before, after) = contents.split_at(offset as usize);
+        assert!(before.is_empty() || before.ends_with('\n'));
+        let since = line(second);
+        let since = &since[..25];
+        for line in before.lines().filter(|line| line.starts_with("2024")) {
+            assert!(&line[..25] < since, "{line} skipped");
+        }
+        assert!(after.is_empty() || after.starts_with("2024"));
+    }
+
+    #[test]
+    fn jumps_close_to_the_start_of_the_range() {
+        let seconds: Vec<i64> = (0..LINES).collect();
+        let path = write_log("ordered", &seconds, 1_000_000);
+        let offset = start(&path, LINES / 2);
+        assert_safe(&path, offset, LINES / 2);
+        // Within the last span of the search before the start of the range
+        let target = fs::read_to_string(&path)
+            .unwrap()
+            .find(&line(LINES / 2))
+            .unwrap();
+        assert!(offset > 0 && target as u64 - offset <= MIN_SPAN);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn lands_on_entries_between_continuation_lines() {
+        let seconds: Vec<i64> = (0..LINES).collect();
+        let path = write_log("continued", &seconds, 3);
+        for second in [1, LINES / 3, LINES / 2 + 1, LINES - 2] {
+            let offset = start(&path, second);
+            assert_safe(&path, offset, second);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn since_outside_of_the_file() {
+        let seconds: Vec<i64> = (0..LINES).collect();
+        let path = write_log("bounds", &seconds, 1_000_000);
+        assert_eq!(start(&path, -3600), 0);
+        assert_eq!(start(&path, 0), 0);
+        let offset = start(&path, LINES + 3600);
+        assert_safe(&path, offset, LINES + 3600);
+        let length = fs::metadata(&path).unwrap().len();
+        assert!(length - offset <= MIN_SPAN);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unordered_files_are_read_from_the_start() {
+        let reversed: Vec<i64> = (0..LINES).rev().collect();
+        let path = write_log("reversed", &reversed, 1_000_000);
+        assert_eq!(start(&path, LINES / 2), 0);
+        fs::remove_file(path).unwrap();
+
+        let shuffled: Vec<i64> = (0..LINES).map(|i| (i * 7919) % LINES).collect();
+        let path = write_log("shuffled", &shuffled, 1_000_000);
+        for second in [1, LINES / 4, LINES / 2, LINES - 1] {
+            assert_eq!(start(&path, second), 0);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn small_files_are_read_from_the_start() {
+        let path = write_log("small", &[0, 1, 2, 3], 2);
+        assert_eq!(start(&path, 3), 0);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,355 @@
+use crate::compression::{self, Compression};
+use crate::json::JsonFields;
+use crate::level::{Level, LevelClassifier};
+use crate::range::TimeRange;
+use crate::search;
+use crate::timestamp::{self, TimestampFormat, TimestampParser};
+use crate::zone::Zone;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+/// Number of lines inspected to detect the timestamp format.
+pub const DETECT_SAMPLE_LINES: usize = 20;
+
+/// A timestamped log entry, tagged with the file it was read from.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub timestamp: DateTime<Utc>,
+    pub level: Option<Level>,
+    /// Index of the source file in the list of files being analysed.
+    pub source: usize,
+    /// The message of a JSON entry, or the whole line, followed by the
+    /// continuation lines of the entry such as a stack trace.
+    pub message: String,
+}
+
+/// A log file to analyse, a member of a zip archive, or standard input when
+/// the path is "-".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFile {
+    pub path: PathBuf,
+    /// Name of the member when the log is read out of a zip archive.
+    pub member: Option<String>,
+}
+
+impl LogFile {
+    /// Returns whether the log is read from standard input.
+    pub fn is_stdin(&self) -> bool {
+        self.member.is_none() && self.path.as_os_str() == "-"
+    }
+}
+
+impl fmt::Display for LogFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.member {
+            Some(member) => write!(f, "{}:{}", self.path.display(), member),
+            None if self.is_stdin() => write!(f, "standard input"),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// Expands the paths and glob patterns given on the command line into the
+/// list of files to read, in the order given. Patterns matching several files
+/// are expanded in alphabetical order, and zip archives, recognised by their
+/// content rather than their name, are expanded into their members.
+///
+/// A path without glob characters is kept as is, so a missing file is reported
+/// when it is opened rather than silently ignored.
+///
+/// # Arguments
+///
+/// * `patterns` - Paths or glob patterns such as "/var/log/app-*.log.gz".
+pub fn expand(patterns: &[String]) -> Vec<LogFile> {
+    let mut files = Vec::new();
+    for path in expand_paths(patterns) {
+        if path.as_os_str() == "-" || compression::sniff_file(&path) != Some(Compression::Zip) {
+            files.push(LogFile { path, member: None });
+            continue;
+        }
+        match compression::zip_members(&path) {
+            Ok(members) => files.extend(members.into_iter().map(|member| LogFile {
+                path: path.clone(),
+                member: Some(member),
+            })),
+            Err(e) => eprintln!("Error reading archive {}: {}", path.display(), e),
+        }
+    }
+    files
+}
+
+// Expands the glob patterns into paths.
+fn expand_paths(patterns: &[String]) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(pattern));
+            continue;
+        }
+        let matches = match glob::glob(pattern) {
+            Ok(matches) => matches,
+            Err(e) => {
+                eprintln!("Invalid pattern {pattern}: {e}");
+                continue;
+            }
+        };
+        let before = paths.len();
+        for entry in matches {
+            match entry {
+                Ok(path) if path.is_file() => paths.push(path),
+                Ok(_) => {}
+                Err(e) => eprintln!("Error reading {}: {}", e.path().display(), e.error()),
+            }
+        }
+        if paths.len() == before {
+            eprintln!("No files match {pattern}");
+        }
+    }
+    paths
+}
+
+// Opens a log file, decompressing it if it is compressed.
+fn open(file: &LogFile) -> io::Result<Box<dyn BufRead>> {
+    let reader: Box<dyn Read> = match &file.member {
+        Some(member) => Box::new(Cursor::new(compression::read_zip_member(
+            &file.path, member,
+        )?)),
+        None if file.is_stdin() => Box::new(io::stdin()),
+        None => Box::new(File::open(&file.path)?),
+    };
+    Ok(Box::new(BufReader::new(compression::decompress(reader)?)))
+}
+
+/// How the lines of every log file are parsed into entries.
+pub struct ParseOptions {
+    /// The timestamp format, or `None` to detect it for each file.
+    pub format: Option<TimestampFormat>,
+    /// The zone of timestamps written without an offset.
+    pub zone: Zone,
+    /// Finds the level of each entry.
+    pub classifier: LevelClassifier,
+    /// The fields of JSON log entries.
+    pub json: JsonFields,
+    /// Matches the first line of each entry, or `None` to start an entry at
+    /// every line with a timestamp.
+    pub entry_start: Option<Regex>,
+    /// The time range of the entries analysed, others are skipped.
+    pub range: TimeRange,
+}
+
+/// Turns the lines of one log file into entries.
+///
+/// Lines that do not start an entry, such as the lines of a stack trace, are
+/// continuation lines appended to the entry before them, so an entry is only
+/// complete once the next one starts or the file ends.
+pub struct EntryParser<'a> {
+    source: usize,
+    parser: TimestampParser,
+    options: &'a ParseOptions,
+    // The entry continuation lines are appended to
+    pending: Option<Entry>,
+}
+
+impl<'a> EntryParser<'a> {
+    /// Creates a new `EntryParser` for a log file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The log file, to report the detected format.
+    /// * `source` - The index entries of the file are tagged with.
+    /// * `sample` - The first lines of the file, to detect the timestamp format.
+    /// * `options` - How lines are parsed.
+    pub fn new(
+        file: &LogFile,
+        source: usize,
+        sample: &[String],
+        options: &'a ParseOptions,
+    ) -> Result<Self, String> {
+        let format = options.format.clone().unwrap_or_else(|| {
+            let detected = timestamp::detect(sample).unwrap_or(TimestampFormat::Rfc3339);
+            eprintln!("Detected timestamp format for {file}: {detected}");
+            detected
+        });
+        Ok(EntryParser {
+            source,
+            parser: TimestampParser::new(format, options.zone)?,
+            options,
+            pending: None,
+        })
+    }
+
+    /// Adds a line of the log file. Returns the previous entry, now complete,
+    /// when the line starts a new one and the entry is in the time range.
+    ///
+    /// A line starts an entry when it matches the start-of-entry pattern, or
+    /// has a timestamp when there is none. An entry whose first line has no
+    /// timestamp is skipped along with its continuation lines. An entry without
+    /// a level takes the first level found in its continuation lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - A line from the log file.
+    pub fn push(&mut self, line: String) -> Option<Entry> {
+        let entry_start = self.options.entry_start.as_ref();
+        if entry_start.is_none_or(|start| start.is_match(&line)) {
+            match self.parse(&line) {
+                Some(entry) => {
+                    let previous = self.pending.replace(entry);
+                    return self.in_range(previous);
+                }
+                None if entry_start.is_some() => return self.flush(),
+                None => {}
+            }
+        }
+        if line.trim().is_empty() {
+            return None;
+        }
+        if let Some(entry) = self.pending.as_mut() {
+            if entry.level.is_none() {
+                entry.level = self.options.classifier.classify(&line);
+            }
+            entry.message.push('\n');
+            entry.message.push_str(&line);
+        }
+        None
+    }
+
+    /// Returns the last entry if it is in the time range, once the file has
+    /// no more lines for now.
+    pub fn flush(&mut self) -> Option<Entry> {
+        let entry = self.pending.take();
+        self.in_range(entry)
+    }
+
+    /// Returns the timestamp of a line starting an entry, or `None` if it has
+    /// none.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - A line from the log file.
+    pub fn timestamp(&self, line: &str) -> Option<DateTime<Utc>> {
+        self.parse(line).map(|entry| entry.timestamp)
+    }
+
+    // Drops an entry outside of the time range.
+    fn in_range(&self, entry: Option<Entry>) -> Option<Entry> {
+        entry.filter(|entry| self.options.range.contains(entry.timestamp))
+    }
+
+    // Returns the entry started by a line, or `None` if it has no timestamp.
+    // Lines holding a JSON object are parsed by field, other lines and JSON
+    // objects without a timestamp field are searched for a timestamp and a
+    // level word.
+    fn parse(&self, line: &str) -> Option<Entry> {
+        let classifier = &self.options.classifier;
+        if let Some(entry) = self.options.json.parse(line, &self.parser, classifier) {
+            return Some(Entry {
+                timestamp: entry.timestamp,
+                level: entry.level,
+                source: self.source,
+                message: entry.message.unwrap_or_else(|| line.to_string()),
+            });
+        }
+        Some(Entry {
+            timestamp: self.parser.parse(line)?,
+            level: classifier.classify(line),
+            source: self.source,
+            message: line.to_string(),
+        })
+    }
+}
+
+/// Reads the timestamped entries of one log file in the order they are written.
+/// Lines without a timestamp are appended to the entry before them, or skipped
+/// before the first entry. Entries outside of the time range are skipped, and
+/// plain files are searched for the start of the range rather than read from
+/// the start.
+pub struct LogReader<'a> {
+    file: LogFile,
+    lines: Box<dyn Iterator<Item = io::Result<String>>>,
+    parser: EntryParser<'a>,
+}
+
+impl<'a> LogReader<'a> {
+    /// Opens a log file for reading.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The log file, possibly compressed with gzip, bzip2, xz or zstd.
+    /// * `source` - The index entries read from the file are tagged with.
+    /// * `options` - How lines are parsed.
+    pub fn open(file: &LogFile, source: usize, options: &'a ParseOptions) -> Result<Self, String> {
+        let reader = open(file).map_err(|e| e.to_string())?;
+
+        // Keep the first lines aside to detect the timestamp format from them
+        let mut lines = lossy_lines(reader);
+        let sample: Vec<_> = lines.by_ref().take(DETECT_SAMPLE_LINES).collect();
+        let sample_lines: Vec<String> = sample.iter().flatten().cloned().collect();
+        let parser = EntryParser::new(file, source, &sample_lines, options)?;
+
+        // Jump close to the start of the time range in plain files instead of reading up to it
+        let start = match options.range.since {
+            Some(since) if file.member.is_none() && !file.is_stdin() => {
+                match compression::sniff_file(&file.path) {
+                    Some(_) => 0,
+                    None => search::start_offset(&file.path, &parser, since).unwrap_or(0),
+                }
+            }
+            _ => 0,
+        };
+        let lines: Box<dyn Iterator<Item = io::Result<String>>> = match start {
+            0 => Box::new(sample.into_iter().chain(lines)),
+            start => {
+                let mut reader = File::open(&file.path).map_err(|e| e.to_string())?;
+                reader
+                    .seek(SeekFrom::Start(start))
+                    .map_err(|e| e.to_string())?;
+                Box::new(lossy_lines(Box::new(BufReader::new(reader))))
+            }
+        };
+
+        Ok(LogReader {
+            file: file.clone(),
+            lines,
+            parser,
+        })
+    }
+}
+
+impl Iterator for LogReader<'_> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    // Corrupt compressed data cannot be read past, stop at the first error
+                    eprintln!("Error reading {}: {}", self.file, error);
+                    self.lines = Box::new(std::iter::empty());
+                    return self.parser.flush();
+                }
+            };
+            if let Some(entry) = self.parser.push(line) {
+                return Some(entry);
+            }
+        }
+        self.parser.flush()
+    }
+}
+
+// Splits a reader into lines without their line endings. Invalid UTF-8 is
+// replaced rather than failing the line, so only read errors are returned.
+fn lossy_lines(reader: Box<dyn BufRead>) -> impl Iterator<Item = io::Result<String>> {
+    reader.split(b'\n').map(|line| {
+        let mut line = line?;
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    })
+}
@@ -0,0 +1,60 @@
+use crate::bucket::Series;
+
+/// Exit status when a bucket has a higher error rate than allowed. Statuses 2
+/// and 3 are left out, clap already exits with 2 on invalid arguments.
+pub const EXIT_ERROR_RATE: i32 = 4;
+
+/// Exit status when a bucket has more errors than allowed.
+pub const EXIT_ERRORS_PER_BUCKET: i32 = 5;
+
+/// Parses an error rate threshold given on the command line, a percentage
+/// between 0 and 100.
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("expected a percentage, got '{value}'"))?;
+    if !(0.0..=100.0).contains(&rate) {
+        return Err(format!(
+            "expected a percentage between 0 and 100, got {rate}"
+        ));
+    }
+    Ok(rate)
+}
+
+/// Checks every bucket against the thresholds, prints the buckets exceeding
+/// them to stderr and returns the exit status: 0 when every bucket is within
+/// the thresholds, otherwise the status of the last threshold exceeded.
+///
+/// # Arguments
+///
+/// * `series` - The counts of every bucket.
+/// * `max_error_rate` - The highest error rate allowed in a bucket, in percent.
+/// * `max_errors` - The highest number of errors allowed in a bucket.
+pub fn check(series: &Series, max_error_rate: Option<f64>, max_errors: Option<u64>) -> i32 {
+    let mut status = 0;
+    for (start, bucket) in series.iter() {
+        let start = series.zone().local(start).format("%Y-%m-%d %H:%M");
+        if let Some(max) = max_error_rate {
+            if bucket.error_rate() > max {
+                status = EXIT_ERROR_RATE;
+                eprintln!(
+                    "[FAIL] Error rate {:.2}% above {max}% in bucket {start} ({} errors in {} entries)",
+                    bucket.error_rate(),
+                    bucket.errors(),
+                    bucket.total
+                );
+            }
+        }
+        if let Some(max) = max_errors {
+            if bucket.errors() > max {
+                status = EXIT_ERRORS_PER_BUCKET;
+                eprintln!(
+                    "[FAIL] {} errors above {max} in bucket {start}",
+                    bucket.errors()
+                );
+            }
+        }
+    }
+    status
+}
@@ -0,0 +1,421 @@
+use crate::zone::{self, Zone};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// How timestamps are written in a log file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 / ISO 8601, e.g. "2023-10-01T00:00:00Z". A space may replace
+    /// the "T" and the offset may be written as `±HH`, `±HHMM` or `±HH:MM`, as
+    /// in "2023-10-01 00:00:00-00".
+    Rfc3339,
+    /// Classic syslog, e.g. "Oct 18 02:05:01". The year is not logged.
+    Syslog,
+    /// Apache common log format, e.g. "[10/Oct/2000:13:55:36 -0700]".
+    Apache,
+    /// Unix epoch at the start of the line, in seconds or milliseconds.
+    Epoch,
+    /// A user supplied strftime-style format, e.g. "%d/%m/%Y %H:%M:%S".
+    Custom(String),
+}
+
+// Built-in formats in the order auto-detection prefers them on a tie
+const BUILT_IN: [TimestampFormat; 4] = [
+    TimestampFormat::Rfc3339,
+    TimestampFormat::Syslog,
+    TimestampFormat::Apache,
+    TimestampFormat::Epoch,
+];
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rfc3339" | "iso" => Ok(TimestampFormat::Rfc3339),
+            "syslog" => Ok(TimestampFormat::Syslog),
+            "apache" | "clf" => Ok(TimestampFormat::Apache),
+            "epoch" => Ok(TimestampFormat::Epoch),
+            custom if custom.contains('%') => {
+                // Fail early on specifiers we cannot turn into a regex
+                strftime_regex(custom)?;
+                Ok(TimestampFormat::Custom(custom.to_string()))
+            }
+            other => Err(format!(
+                "unknown timestamp format '{other}', expected rfc3339, syslog, apache, epoch or a strftime format"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TimestampFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimestampFormat::Rfc3339 => write!(f, "rfc3339"),
+            TimestampFormat::Syslog => write!(f, "syslog"),
+            TimestampFormat::Apache => write!(f, "apache"),
+            TimestampFormat::Epoch => write!(f, "epoch"),
+            TimestampFormat::Custom(format) => write!(f, "{format}"),
+        }
+    }
+}
+
+/// Finds and parses the timestamp of a log line in a given format.
+pub struct TimestampParser {
+    format: TimestampFormat,
+    regex: Regex,
+    zone: Zone,
+}
+
+impl TimestampParser {
+    /// Creates a new `TimestampParser` for the given format.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format timestamps are written in.
+    /// * `zone` - The zone of timestamps written without an offset.
+    pub fn new(format: TimestampFormat, zone: Zone) -> Result<Self, String> {
+        // Each regex captures the timestamp without its surroundings in group 1,
+        // and its offset, if any, in group 2
+        let pattern = match &format {
+            TimestampFormat::Rfc3339 => {
+                r"(\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?)([Zz]|[+-]\d{2}(?::?\d{2})?)?"
+                    .to_string()
+            }
+            TimestampFormat::Syslog => {
+                r"\b([A-Z][a-z]{2} +\d{1,2} \d{2}:\d{2}:\d{2})\b".to_string()
+            }
+            TimestampFormat::Apache => {
+                r"\[(\d{2}/[A-Z][a-z]{2}/\d{4}:\d{2}:\d{2}:\d{2}) ([+-]\d{4})\]".to_string()
+            }
+            TimestampFormat::Epoch => r"^\[?(\d{13}|\d{10}(?:\.\d+)?)\b".to_string(),
+            TimestampFormat::Custom(custom) => format!("({})", strftime_regex(custom)?),
+        };
+        let regex = Regex::new(&pattern).map_err(|e| e.to_string())?;
+        Ok(TimestampParser {
+            format,
+            regex,
+            zone,
+        })
+    }
+
+    /// Extracts the first timestamp from a log line, if any, as an instant.
+    ///
+    /// The offset written with the timestamp is applied. Timestamps without
+    /// one are taken as wall clock time in the zone of the parser, except
+    /// epoch timestamps which are always UTC.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - A line from the log file.
+    pub fn parse(&self, line: &str) -> Option<DateTime<Utc>> {
+        let captures = self.regex.captures(line)?;
+        let text = captures.get(1)?.as_str();
+        let offset = match captures.get(2) {
+            Some(offset) => Some(zone::parse_offset(offset.as_str())?),
+            None => None,
+        };
+        let local = match &self.format {
+            TimestampFormat::Rfc3339 => {
+                let text = text.replacen('T', " ", 1).replacen(',', ".", 1);
+                NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f").ok()?
+            }
+            TimestampFormat::Syslog => parse_syslog(text, self.zone)?,
+            TimestampFormat::Apache => {
+                NaiveDateTime::parse_from_str(text, "%d/%b/%Y:%H:%M:%S").ok()?
+            }
+            TimestampFormat::Epoch => {
+                let seconds: f64 = text.parse().ok()?;
+                // 13 digits are milliseconds
+                let millis = match text.len() {
+                    13 => seconds as i64,
+                    _ => (seconds * 1000.0).round() as i64,
+                };
+                return DateTime::from_timestamp_millis(millis);
+            }
+            TimestampFormat::Custom(custom) => {
+                if let Ok(timestamp) = DateTime::parse_from_str(text, custom) {
+                    return Some(timestamp.to_utc());
+                }
+                parse_custom(text, custom)?
+            }
+        };
+        match offset {
+            Some(offset) => offset
+                .from_local_datetime(&local)
+                .single()
+                .map(|t| t.to_utc()),
+            None => self.zone.instant(local),
+        }
+    }
+}
+
+/// Guesses the timestamp format from a sample of lines, usually the first
+/// lines of the file. The built-in format matching the most lines wins.
+///
+/// # Arguments
+///
+/// * `sample` - Lines to try every built-in format on.
+pub fn detect(sample: &[String]) -> Option<TimestampFormat> {
+    let mut best = None;
+    let mut best_count = 0;
+    for format in BUILT_IN {
+        let parser = TimestampParser::new(format.clone(), Zone::default()).ok()?;
+        let count = sample
+            .iter()
+            .filter(|line| parser.parse(line).is_some())
+            .count();
+        if count > best_count {
+            best = Some(format);
+            best_count = count;
+        }
+    }
+    best
+}
+
+// Syslog omits the year, assume the current one unless that puts the entry
+// in the future, which happens when reading December logs in January. The
+// current time is taken in the zone the timestamps are in.
+fn parse_syslog(text: &str, zone: Zone) -> Option<NaiveDateTime> {
+    let now = zone.local(Utc::now());
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let parsed = |year: i32| {
+        NaiveDateTime::parse_from_str(&format!("{year} {text}"), "%Y %b %d %H:%M:%S").ok()
+    };
+    let timestamp = parsed(now.year())?;
+    if timestamp > now + TimeDelta::days(1) {
+        return parsed(now.year() - 1);
+    }
+    Some(timestamp)
+}
+
+// Parses a user supplied format without an offset, which may or may not contain a time.
+fn parse_custom(text: &str, format: &str) -> Option<NaiveDateTime> {
+    if let Ok(timestamp) = NaiveDateTime::parse_from_str(text, format) {
+        return Some(timestamp);
+    }
+    NaiveDate::parse_from_str(text, format)
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+}
+
+/// Translates a strftime-style format into a regex matching the timestamps it
+/// produces, so they can be found anywhere in a line.
+///
+/// # Arguments
+///
+/// * `format` - A format such as "%Y-%m-%d %H:%M:%S".
+fn strftime_regex(format: &str) -> Result<String, String> {
+    let mut pattern = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            if c.is_whitespace() {
+                pattern.push_str(r"\s+");
+            } else {
+                pattern.push_str(&regex::escape(&c.to_string()));
+            }
+            continue;
+        }
+        let spec = match chars.next() {
+            // %.f, %.3f and friends
+            Some('.') => {
+                let mut spec = String::from(".");
+                for c in chars.by_ref() {
+                    spec.push(c);
+                    if c == 'f' {
+                        break;
+                    }
+                }
+                spec
+            }
+            Some(':') => format!(":{}", chars.next().unwrap_or_default()),
+            Some(c) => c.to_string(),
+            None => return Err("format ends with a lone '%'".to_string()),
+        };
+        let part = match spec.as_str() {
+            "Y" => r"[+-]?\d{4}",
+            "C" | "y" | "g" => r"\d{2}",
+            "m" | "d" | "H" | "I" | "M" | "S" | "U" | "W" | "V" => r"\d{1,2}",
+            "e" | "k" | "l" => r" ?\d{1,2}",
+            "j" => r"\d{1,3}",
+            "b" | "h" | "a" => r"[A-Za-z]{3}",
+            "B" | "A" => r"[A-Za-z]+",
+            "p" | "P" => r"[AaPp][Mm]",
+            "f" => r"\d+",
+            "z" => r"[+-]\d{2}:?\d{2}",
+            ":z" => r"[+-]\d{2}:\d{2}",
+            "Z" => r"[A-Za-z]+",
+            "s" => r"\d+",
+            "T" | "X" => r"\d{2}:\d{2}:\d{2}",
+            "R" => r"\d{2}:\d{2}",
+            "F" => r"\d{4}-\d{2}-\d{2}",
+            "D" | "x" => r"\d{2}/\d{2}/\d{2}",
+            "%" => "%",
+            "n" | "t" => r"\s+",
+            spec if spec.starts_with('.') => r"\.\d+",
+            other => return Err(format!("unsupported timestamp specifier %{other}")),
+        };
+        pattern.push_str(part);
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().to_utc()
+    }
+
+    fn parse(format: TimestampFormat, zone: &str, line: &str) -> Option<DateTime<Utc>> {
+        TimestampParser::new(format, zone.parse().unwrap())
+            .unwrap()
+            .parse(line)
+    }
+
+    #[test]
+    fn rfc3339_offsets() {
+        let rfc3339 = |line| parse(TimestampFormat::Rfc3339, "UTC", line);
+        let expected = Some(utc("2023-10-01T10:00:00Z"));
+        assert_eq!(rfc3339("2023-10-01T10:00:00Z INFO up"), expected);
+        assert_eq!(rfc3339("2023-10-01T10:00:00z INFO up"), expected);
+        assert_eq!(rfc3339("2023-10-01T12:00:00+02 INFO up"), expected);
+        assert_eq!(rfc3339("2023-10-01T15:30:00+0530 INFO up"), expected);
+        assert_eq!(rfc3339("2023-10-01T05:00:00-05:00 INFO up"), expected);
+        assert_eq!(rfc3339("2023-10-01 10:00:00-00 INFO up"), expected);
+    }
+
+    #[test]
+    fn rfc3339_fractions_and_zone() {
+        assert_eq!(
+            parse(
+                TimestampFormat::Rfc3339,
+                "UTC",
+                "at 2023-10-01T10:00:00,250Z"
+            ),
+            Some(utc("2023-10-01T10:00:00.250Z"))
+        );
+        // Without an offset the timestamp is in the zone of the parser
+        assert_eq!(
+            parse(
+                TimestampFormat::Rfc3339,
+                "Europe/Paris",
+                "2023-10-01 10:00:00.5 up"
+            ),
+            Some(utc("2023-10-01T08:00:00.5Z"))
+        );
+        assert_eq!(
+            parse(TimestampFormat::Rfc3339, "UTC", "no timestamp here"),
+            None
+        );
+    }
+
+    #[test]
+    fn syslog_without_year() {
+        let timestamp = parse(
+            TimestampFormat::Syslog,
+            "UTC",
+            "Oct  8 02:05:01 host cron[1]: ok",
+        )
+        .unwrap()
+        .naive_utc();
+        assert_eq!((timestamp.month(), timestamp.day()), (10, 8));
+        assert_eq!(
+            (timestamp.hour(), timestamp.minute(), timestamp.second()),
+            (2, 5, 1)
+        );
+        assert!(timestamp <= Utc::now().naive_utc() + TimeDelta::days(1));
+    }
+
+    #[test]
+    fn syslog_year_follows_the_zone() {
+        // An entry logged just now is in the current year of its own zone,
+        // even when the machine is already in another year
+        for zone in ["+14:00", "-12:00", "Pacific/Kiritimati"] {
+            let zone: Zone = zone.parse().unwrap();
+            let now = zone.local(Utc::now());
+            let text = now.format("%b %e %H:%M:%S").to_string();
+            assert_eq!(parse_syslog(&text, zone).unwrap().year(), now.year());
+        }
+    }
+
+    #[test]
+    fn apache_with_offset() {
+        assert_eq!(
+            parse(
+                TimestampFormat::Apache,
+                "UTC",
+                r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200"#
+            ),
+            Some(utc("2000-10-10T20:55:36Z"))
+        );
+    }
+
+    #[test]
+    fn epoch_seconds_and_milliseconds() {
+        let epoch = |line| parse(TimestampFormat::Epoch, "Europe/Paris", line);
+        let expected = Some(utc("2023-11-14T22:13:20Z"));
+        assert_eq!(epoch("1700000000 INFO up"), expected);
+        assert_eq!(epoch("[1700000000000] INFO up"), expected);
+        assert_eq!(
+            epoch("1700000000.5 INFO up"),
+            Some(utc("2023-11-14T22:13:20.5Z"))
+        );
+        assert_eq!(epoch("INFO 1700000000"), None);
+    }
+
+    #[test]
+    fn custom_formats() {
+        let custom =
+            |format: &str, line| parse(TimestampFormat::from_str(format).unwrap(), "UTC", line);
+        assert_eq!(
+            custom("%d/%m/%Y %H:%M:%S", "app: 01/10/2023 10:00:00 ERROR x"),
+            Some(utc("2023-10-01T10:00:00Z"))
+        );
+        assert_eq!(
+            custom("%Y-%m-%d %H:%M:%S %z", "2023-10-01 12:00:00 +0200 up"),
+            Some(utc("2023-10-01T10:00:00Z"))
+        );
+        assert_eq!(
+            custom("%b %e %Y", "Oct  1 2023 up"),
+            Some(utc("2023-10-01T00:00:00Z"))
+        );
+        assert_eq!(
+            custom("%Y%m%dT%H%M%S%.3f", "20231001T100000.250 up"),
+            Some(utc("2023-10-01T10:00:00.250Z"))
+        );
+    }
+
+    #[test]
+    fn strftime_regex_escapes_and_rejects() {
+        assert_eq!(strftime_regex("[%H:%M]").unwrap(), r"\[\d{1,2}:\d{1,2}\]");
+        assert_eq!(
+            strftime_regex("%F %T").unwrap(),
+            r"\d{4}-\d{2}-\d{2}\s+\d{2}:\d{2}:\d{2}"
+        );
+        assert_eq!(strftime_regex("%:z").unwrap(), r"[+-]\d{2}:\d{2}");
+        assert_eq!(strftime_regex("100%%").unwrap(), "100%");
+        assert!(strftime_regex("%Y %Q").is_err());
+        assert!(strftime_regex("%H:%").is_err());
+        assert!(TimestampFormat::from_str("%Q").is_err());
+        assert!(TimestampFormat::from_str("yyyy-mm-dd").is_err());
+    }
+
+    #[test]
+    fn detect_picks_the_most_common_format() {
+        let sample: Vec<String> = [
+            "Oct  1 10:00:00 host a",
+            "2023-10-01T10:00:00Z b",
+            "2023-10-01T10:00:01Z c",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(detect(&sample), Some(TimestampFormat::Rfc3339));
+        assert_eq!(detect(&["nothing".to_string()]), None);
+    }
+}
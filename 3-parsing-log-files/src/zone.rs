@@ -0,0 +1,197 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// A time zone timestamps are normalized to: a fixed offset from UTC or a
+/// named IANA zone such as "Europe/Paris", which follows daylight saving time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Zone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Zone {
+    /// Returns the wall clock time of an instant in this zone.
+    ///
+    /// # Arguments
+    ///
+    /// * `instant` - The instant to convert.
+    pub fn local(self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Fixed(offset) => instant.with_timezone(&offset).naive_local(),
+            Zone::Named(tz) => instant.with_timezone(&tz).naive_local(),
+        }
+    }
+
+    /// Returns the instant a wall clock time in this zone stands for.
+    ///
+    /// A time repeated when clocks go back is taken as the first one. A time
+    /// skipped when clocks go forward is taken with the offset in use before
+    /// the change.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - The wall clock time, as written in a log without an offset.
+    pub fn instant(self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Fixed(offset) => offset
+                .from_local_datetime(&local)
+                .single()
+                .map(|t| t.to_utc()),
+            Zone::Named(tz) => tz
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|t| t.to_utc())
+                .or_else(|| {
+                    let before = tz.offset_from_utc_datetime(&(local - TimeDelta::days(1)));
+                    before
+                        .fix()
+                        .from_local_datetime(&local)
+                        .single()
+                        .map(|t| t.to_utc())
+                }),
+        }
+    }
+
+    /// Formats an instant as an RFC 3339 timestamp in this zone, e.g.
+    /// "2023-10-01T02:00:00+02:00".
+    ///
+    /// # Arguments
+    ///
+    /// * `instant` - The instant to format.
+    pub fn format_instant(self, instant: DateTime<Utc>) -> String {
+        match self {
+            Zone::Fixed(offset) => instant.with_timezone(&offset).to_rfc3339(),
+            Zone::Named(tz) => instant.with_timezone(&tz).to_rfc3339(),
+        }
+    }
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Zone::Fixed(Utc.fix())
+    }
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("utc") {
+            return Ok(Zone::default());
+        }
+        if let Some(offset) = parse_offset(s) {
+            return Ok(Zone::Fixed(offset));
+        }
+        s.parse::<Tz>().map(Zone::Named).map_err(|_| {
+            format!("unknown time zone '{s}', expected UTC, an offset such as +02:00 or a name such as Europe/Paris")
+        })
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Zone::Fixed(offset) if offset.local_minus_utc() == 0 => write!(f, "UTC"),
+            Zone::Fixed(offset) => write!(f, "{offset}"),
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// Parses a UTC offset written as `Z`, `±HH`, `±HHMM` or `±HH:MM`.
+///
+/// # Arguments
+///
+/// * `text` - The offset, e.g. "-07", "+0530" or "+05:30".
+pub fn parse_offset(text: &str) -> Option<FixedOffset> {
+    if text.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match text.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let rest = &text[1..];
+    if !rest.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return None;
+    }
+    let (hours, minutes) = match rest.len() {
+        2 => (rest, "00"),
+        4 => rest.split_at(2),
+        5 if rest.as_bytes()[2] == b':' => (&rest[..2], &rest[3..]),
+        _ => return None,
+    };
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(text: &str) -> Option<i32> {
+        parse_offset(text).map(|offset| offset.local_minus_utc())
+    }
+
+    #[test]
+    fn offset_spellings() {
+        assert_eq!(seconds("Z"), Some(0));
+        assert_eq!(seconds("z"), Some(0));
+        assert_eq!(seconds("+02"), Some(7200));
+        assert_eq!(seconds("-07"), Some(-25200));
+        assert_eq!(seconds("+0530"), Some(19800));
+        assert_eq!(seconds("-0930"), Some(-34200));
+        assert_eq!(seconds("+05:30"), Some(19800));
+        assert_eq!(seconds("-00:00"), Some(0));
+    }
+
+    #[test]
+    fn invalid_offsets() {
+        for text in [
+            "", "+", "02", "+2", "+123", "+05:3", "+0560", "+05-30", "+0a:30", "+25", "UTC",
+        ] {
+            assert_eq!(seconds(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn zone_names_and_local_times() {
+        let paris: Zone = "Europe/Paris".parse().unwrap();
+        let instant = DateTime::parse_from_rfc3339("2023-07-01T10:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(paris.local(instant).to_string(), "2023-07-01 12:00:00");
+        assert_eq!(paris.instant(paris.local(instant)), Some(instant));
+        assert_eq!(paris.format_instant(instant), "2023-07-01T12:00:00+02:00");
+        assert_eq!("+05:30".parse::<Zone>().unwrap().to_string(), "+05:30");
+        assert_eq!("utc".parse::<Zone>().unwrap(), Zone::default());
+        assert!("Mars/Olympus".parse::<Zone>().is_err());
+    }
+
+    #[test]
+    fn daylight_saving_changes() {
+        let paris: Zone = "Europe/Paris".parse().unwrap();
+        let local = |text| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        // 02:30 happens twice when clocks go back, the first one is taken
+        assert_eq!(
+            paris
+                .instant(local("2023-10-29 02:30"))
+                .map(|t| paris.format_instant(t)),
+            Some("2023-10-29T02:30:00+02:00".to_string())
+        );
+        // 02:30 is skipped when clocks go forward, the offset before the change is used
+        assert_eq!(
+            paris
+                .instant(local("2023-03-26 02:30"))
+                .map(|t| t.to_rfc3339()),
+            Some("2023-03-26T01:30:00+00:00".to_string())
+        );
+    }
+}
@@ -6,9 +6,10 @@
 
 use glob::glob;
 use serde::Deserialize;
-use serde_json;
+use std::ffi::OsStr;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
 
 // Load the rules.json file to provide configs
 const JSON: &str = include_str!("../../compliance-rules/rules.json");
@@ -70,28 +71,30 @@ fn apply_rules(rules: Vec<ComplianceRule>) {
     // Iterate over the rules and apply them to the file system
     println!("Applying compliance rules...");
     for rule in rules {
-        let mut seen_files: Vec<String> = Vec::new();
+        // Paths are kept as OsStr so names that are not valid UTF-8 are
+        // compared byte for byte instead of panicking
+        let mut seen_files: Vec<PathBuf> = Vec::new();
         for entry in glob(&rule.path_regex).expect("Failed to read glob pattern") {
             match entry {
                 Ok(path) => {
                     if path.is_dir() {
                         continue;
                     }
-                    seen_files.push(path.to_str().unwrap().to_string());
                     let metadata = fs::metadata(&path).unwrap();
                     if metadata.permissions().mode() != rule.file_permissions {
-                        println!(
-                            "[FAIL] Incorrect file permissions for path: {:?}",
-                            path.display()
-                        );
+                        println!("[FAIL] Incorrect file permissions for path: {:?}", path);
                     }
+                    seen_files.push(path);
                 }
                 Err(e) => println!("{:?}", e),
             }
         }
 
         for file in rule.required_files {
-            if !seen_files.contains(&file) {
+            if !seen_files
+                .iter()
+                .any(|seen| seen.as_os_str() == OsStr::new(&file))
+            {
                 println!(
                     "[FAIL] Required file {file} not found in path {}: ",
                     rule.path_regex